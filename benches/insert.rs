@@ -0,0 +1,113 @@
+//! Compares row-by-row and batched inserts over synthetic tables.
+
+use std::convert::Infallible;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rbk_db::bench::{Color, Database, Element, InsertMode, Part, PartCategory, PartMaterial, Rgb};
+use tempfile::TempDir;
+
+const ROWS: usize = 20_000;
+const MODES: [InsertMode; 2] = [InsertMode::RowByRow, InsertMode::Batched];
+
+fn part_num(i: usize) -> String {
+    format!("{}pr{:04}", 3000 + i % 100, i / 100)
+}
+
+fn parts() -> Vec<Part> {
+    (0..ROWS)
+        .map(|i| Part {
+            part_num: part_num(i),
+            name: format!("Brick 1 x {} with print {i}", i % 16 + 1),
+            part_cat_id: 1,
+            part_material: PartMaterial::Plastic,
+        })
+        .collect()
+}
+
+fn elements() -> Vec<Element> {
+    (0..ROWS)
+        .map(|i| Element {
+            element_id: (4_000_000 + i).to_string(),
+            part_num: part_num(i),
+            color_id: 0,
+            design_id: Some(3000 + (i % 100) as i32),
+        })
+        .collect()
+}
+
+/// Opens an empty database, in a directory that must outlive it.
+fn open() -> (TempDir, Database) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut db = Database::open(dir.path().join("bench.db")).unwrap();
+    db.insert_many([Ok::<_, Infallible>(PartCategory {
+        id: 1,
+        name: "Bricks Printed".to_owned(),
+    })])
+    .unwrap();
+    db.insert_many([Ok::<_, Infallible>(Color {
+        id: 0,
+        name: "Black".to_owned(),
+        rgb: Rgb { r: 5, g: 19, b: 29 },
+        is_trans: false,
+        num_parts: 0,
+        num_sets: 0,
+        first_year: None,
+        last_year: None,
+    })])
+    .unwrap();
+    (dir, db)
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.sample_size(10);
+
+    let parts = parts();
+    let elements = elements();
+    for mode in MODES {
+        group.bench_with_input(
+            BenchmarkId::new("parts", format!("{mode:?}")),
+            &mode,
+            |b, &mode| {
+                b.iter_batched(
+                    open,
+                    |(_dir, mut db)| {
+                        db.insert_many_with_mode(
+                            parts.iter().cloned().map(Ok::<_, Infallible>),
+                            mode,
+                        )
+                        .unwrap()
+                    },
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("elements", format!("{mode:?}")),
+            &mode,
+            |b, &mode| {
+                b.iter_batched(
+                    || {
+                        let (dir, mut db) = open();
+                        db.insert_many(parts.iter().cloned().map(Ok::<_, Infallible>))
+                            .unwrap();
+                        (dir, db)
+                    },
+                    |(_dir, mut db)| {
+                        db.insert_many_with_mode(
+                            elements.iter().cloned().map(Ok::<_, Infallible>),
+                            mode,
+                        )
+                        .unwrap()
+                    },
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, insert);
+criterion_main!(benches);
@@ -1,17 +1,78 @@
+mod check;
+mod collection;
 mod completion;
+mod config;
 mod dump;
+mod feed;
+mod fetch_external_ids;
+mod images;
+mod import;
+mod shell;
+mod show;
+mod site;
+mod substitutes;
+mod sync_user;
+mod unpack;
+mod watch;
+
+pub use self::completion::complete_dynamically;
 
 #[derive(Debug, clap::Parser)]
 pub enum Command {
+    /// Check the database for integrity and data-quality issues.
+    Check(check::Args),
+    /// Query the parts of a collection of sets.
+    Collection(collection::Args),
     /// Generate completion scripts.
     Completion(completion::Args),
+    /// Inspect the configuration.
+    Config(config::Args),
     /// Dump the Rebrickable API tables to an SQLite database.
     Dump(dump::Args),
+    /// Write feeds of the sets, minifigures and themes added since the
+    /// previous build.
+    Feed(feed::Args),
+    /// Fetch external identifiers of colours and parts from the Rebrickable API.
+    FetchExternalIds(fetch_external_ids::Args),
+    /// Manage the local cache of set, minifigure and part images.
+    Images(images::Args),
+    /// Import a parts list from a file.
+    Import(import::Args),
+    /// Run an interactive SQL shell against the database.
+    Shell(shell::Args),
+    /// Show a set, a part or colours from the database.
+    Show(show::Args),
+    /// Generate a static HTML catalogue of the database.
+    Site(site::Args),
+    /// List the parts that can substitute a part.
+    Substitutes(substitutes::Args),
+    /// Synchronize the set lists, part lists and lost parts of a Rebrickable
+    /// user.
+    SyncUser(sync_user::Args),
+    /// Install a database from a package created by `dump --package`.
+    #[command(visible_alias = "fetch-package")]
+    Unpack(unpack::Args),
+    /// Rebuild the database whenever the Rebrickable archives change.
+    Watch(watch::Args),
 }
 
 pub async fn run(command: Command) -> anyhow::Result<()> {
     match command {
+        Command::Check(args) => check::run(args).await,
+        Command::Collection(args) => collection::run(args).await,
         Command::Completion(args) => completion::run(args).await,
+        Command::Config(args) => config::run(args).await,
         Command::Dump(args) => dump::run(args).await,
+        Command::Feed(args) => feed::run(args).await,
+        Command::FetchExternalIds(args) => fetch_external_ids::run(args).await,
+        Command::Images(args) => images::run(args).await,
+        Command::Import(args) => import::run(args).await,
+        Command::Shell(args) => shell::run(args).await,
+        Command::Show(args) => show::run(args).await,
+        Command::Site(args) => site::run(args).await,
+        Command::Substitutes(args) => substitutes::run(args).await,
+        Command::SyncUser(args) => sync_user::run(args).await,
+        Command::Unpack(args) => unpack::run(args).await,
+        Command::Watch(args) => watch::run(args).await,
     }
 }
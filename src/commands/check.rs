@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use crate::database::{Database, Severity};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// Fail if any rule of this severity or higher is broken.
+    #[arg(long, value_enum, default_value_t = Severity::Error)]
+    fail_on: Severity,
+    /// The number of examples to show for each broken rule.
+    #[arg(long, default_value_t = 5)]
+    examples: usize,
+    /// The database file to check.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let db = Database::open_read_only(&args.database)?;
+    let findings = db.check(args.examples)?;
+
+    for finding in &findings {
+        let rule = finding.rule;
+        println!(
+            "[{}] {}: {} ({})",
+            rule.severity, rule.name, rule.description, finding.count
+        );
+        for example in &finding.examples {
+            println!("  {example}");
+        }
+        if finding.count > finding.examples.len() {
+            println!("  ... and {} more", finding.count - finding.examples.len());
+        }
+    }
+
+    let failed = findings
+        .iter()
+        .filter(|finding| finding.rule.severity >= args.fail_on)
+        .count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{failed} rule(s) of severity {} or higher are broken",
+            args.fail_on
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    database::Database,
+    rebrickable::list::{PartListEntry, SetListEntry},
+    types::InventoryVersion,
+};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The collection command to execute.
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Compute the loose parts obtained by parting out the owned sets.
+    Parts(PartsArgs),
+}
+
+#[derive(Debug, clap::Parser)]
+struct PartsArgs {
+    /// Exclude spare parts from the inventories.
+    #[arg(long)]
+    exclude_spares: bool,
+    /// The versions of the inventories of the owned sets to part out:
+    /// `latest`, `all` or a version number.
+    #[arg(long, default_value_t)]
+    inventory_version: InventoryVersion,
+    /// The file to write the parts list to, in Rebrickable's CSV format.
+    /// Defaults to the standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// The database file to query.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+    /// The owned sets, as a set list exported from Rebrickable.
+    sets: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    match args.command {
+        Command::Parts(args) => run_parts(args),
+    }
+}
+
+fn run_parts(args: PartsArgs) -> anyhow::Result<()> {
+    let sets = SetListEntry::read_all(File::open(&args.sets)?)?;
+    let mut db = Database::open_read_only(&args.database)?;
+    let collection = db.collection_parts(&sets, args.inventory_version, !args.exclude_spares)?;
+    for set_num in &collection.unknown_sets {
+        tracing::warn!("set {set_num} has no matching inventory in the database");
+    }
+
+    let mut category_totals: Vec<(&str, u32)> = Vec::new();
+    for part in &collection.parts {
+        match category_totals.last_mut() {
+            Some((category, total)) if *category == part.category => *total += part.quantity,
+            _ => category_totals.push((&part.category, part.quantity)),
+        }
+    }
+    for (category, total) in category_totals {
+        tracing::info!("{total} parts in category {category}");
+    }
+
+    let entries: Vec<_> = collection
+        .parts
+        .into_iter()
+        .map(|part| PartListEntry {
+            part_num: part.part_num,
+            color_id: part.color_id,
+            quantity: part.quantity,
+        })
+        .collect();
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    PartListEntry::write_all(output, &entries)?;
+    Ok(())
+}
@@ -1,12 +1,49 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use clap_complete::{
+    CompletionCandidate,
+    env::{CompleteEnv, Shells},
+};
+
+use crate::{
+    config::{self, Config},
+    database::{Database, IdentifierKind},
+};
+
+/// The environment variable through which the shell requests dynamic
+/// completions.
+const COMPLETE_ENV: &str = "COMPLETE";
+
+/// The shell requesting completions, as `CompleteEnv` clears its variable.
+static COMPLETING_SHELL: OnceLock<OsString> = OnceLock::new();
+
+/// The maximum number of identifiers suggested at once.
+const MAX_CANDIDATES: usize = 200;
+
 #[derive(Debug, clap::Parser)]
 pub struct Args {
+    /// Generate a script registering dynamic completions instead, which call
+    /// back into the program to complete set numbers, part numbers and colour
+    /// names from the database.
+    #[arg(long)]
+    dynamic: bool,
     /// The shell for which to generate the completion script.
     shell: clap_complete::Shell,
 }
 
 pub async fn run(args: Args) -> anyhow::Result<()> {
-    generate_completions(args.shell);
-    Ok(())
+    if args.dynamic {
+        generate_registration(args.shell, &mut std::io::stdout())
+    } else {
+        generate_completions(args.shell);
+        Ok(())
+    }
 }
 
 fn generate_completions(shell: clap_complete::Shell) {
@@ -17,3 +54,266 @@ fn generate_completions(shell: clap_complete::Shell) {
         &mut std::io::stdout(),
     );
 }
+
+fn generate_registration(shell: clap_complete::Shell, out: &mut dyn Write) -> anyhow::Result<()> {
+    let name = shell.to_string();
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&name)
+        .ok_or_else(|| anyhow::anyhow!("no dynamic completions for {name}"))?;
+    let bin = clap::crate_name!();
+    completer.write_registration(COMPLETE_ENV, bin, bin, bin, out)?;
+    Ok(())
+}
+
+/// Answers the completion requests of the scripts registered by
+/// `completion --dynamic`, exiting when done.
+///
+/// This must run before anything is written to the standard output.
+pub fn complete_dynamically() {
+    if let Some(shell) = env::var_os(COMPLETE_ENV) {
+        let _ = COMPLETING_SHELL.set(shell);
+    }
+    CompleteEnv::with_factory(<crate::Args as clap::CommandFactory>::command)
+        .var(COMPLETE_ENV)
+        .complete();
+}
+
+/// Completes set numbers from the database.
+pub(super) fn set_nums(current: &OsStr) -> Vec<CompletionCandidate> {
+    identifiers(IdentifierKind::Set, current)
+}
+
+/// Completes part numbers from the database.
+pub(super) fn part_nums(current: &OsStr) -> Vec<CompletionCandidate> {
+    identifiers(IdentifierKind::Part, current)
+}
+
+/// Completes colour names from the database.
+pub(super) fn color_names(current: &OsStr) -> Vec<CompletionCandidate> {
+    identifiers(IdentifierKind::Color, current)
+}
+
+fn identifiers(kind: IdentifierKind, current: &OsStr) -> Vec<CompletionCandidate> {
+    let database = completion_database(env::args_os(), |name| env::var_os(name));
+    // Bash inserts candidates as is, while other shells quote them.
+    let escape = COMPLETING_SHELL.get().is_some_and(|shell| shell == "bash");
+    candidates(&database, kind, current, escape)
+}
+
+fn candidates(
+    database: &Path,
+    kind: IdentifierKind,
+    current: &OsStr,
+    escape: bool,
+) -> Vec<CompletionCandidate> {
+    let Some(prefix) = current.to_str() else {
+        return Vec::new();
+    };
+    // Failing to complete must not disturb the shell, so errors only leave
+    // the candidates empty.
+    let identifiers = Database::open_read_only(database)
+        .and_then(|db| db.identifiers(kind, &unquote(prefix), MAX_CANDIDATES))
+        .unwrap_or_default();
+    identifiers
+        .into_iter()
+        .map(|(identifier, name)| {
+            let help = match kind {
+                IdentifierKind::Color => format!("#{name}"),
+                IdentifierKind::Set | IdentifierKind::Part => name,
+            };
+            let value = if escape {
+                shell_escape(&identifier)
+            } else {
+                identifier
+            };
+            CompletionCandidate::new(value).help(Some(help.into()))
+        })
+        .collect()
+}
+
+/// Removes the quotes and backslashes of a word being completed, which shells
+/// pass as typed, e.g. `"Dark B` or `Dark\ B` for `Dark B`.
+fn unquote(word: &str) -> String {
+    let word = word.strip_prefix(['"', '\'']).unwrap_or(word);
+    let mut unquoted = String::with_capacity(word.len());
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            _ => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// Escapes the characters of a word that are special to the shell, such as
+/// the spaces of `Dark Bluish Gray`.
+fn shell_escape(word: &str) -> String {
+    let mut escaped = String::with_capacity(word.len());
+    for c in word.chars() {
+        if !(c.is_alphanumeric() || "-_.,:/+=@%".contains(c)) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Returns the database to complete from: the `--database` of the command
+/// line being completed, or else `RBK_DB_DATABASE`, the top-level `database`
+/// setting of the configuration file, or `rebrickable.db`.
+fn completion_database<I, F>(args: I, env: F) -> PathBuf
+where
+    I: IntoIterator<Item = OsString>,
+    F: Fn(&str) -> Option<OsString>,
+{
+    // The command line being completed follows the first `--`.
+    let args: Vec<_> = args
+        .into_iter()
+        .skip_while(|arg| arg != "--")
+        .skip(1)
+        .collect();
+    config::raw_arg(args.iter().cloned(), "--database")
+        .or_else(|| env("RBK_DB_DATABASE"))
+        .map(PathBuf::from)
+        .or_else(|| {
+            let config = Config::load_from(args).ok()?;
+            config
+                .settings(&[])
+                .remove("database")?
+                .into_iter()
+                .next()
+                .map(PathBuf::from)
+        })
+        .unwrap_or_else(|| PathBuf::from("rebrickable.db"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{OsStr, OsString},
+        fs,
+        path::PathBuf,
+    };
+
+    use rusqlite::Connection;
+    use tempfile::tempdir;
+
+    use crate::database::{IdentifierKind, tests::write_fixture};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn completion_database() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let config = dir.path().join("config.toml");
+        fs::write(&config, "")?;
+        let config = config.to_str().unwrap();
+        let no_env = |_: &str| None;
+        let env = |name: &str| (name == "RBK_DB_DATABASE").then(|| "env.db".into());
+
+        // Only the command line being completed is considered.
+        let completed = args(&[
+            "rbk-db",
+            "--database",
+            "ignored.db",
+            "--",
+            "rbk-db",
+            "--config",
+            config,
+            "show",
+            "--database",
+            "arg.db",
+            "set",
+            "10",
+        ]);
+        assert_eq!(
+            super::completion_database(completed.clone(), env),
+            PathBuf::from("arg.db")
+        );
+        let completed = args(&["rbk-db", "--", "rbk-db", "--config", config, "show", "set"]);
+        assert_eq!(
+            super::completion_database(completed.clone(), env),
+            PathBuf::from("env.db")
+        );
+        assert_eq!(
+            super::completion_database(completed.clone(), no_env),
+            PathBuf::from("rebrickable.db")
+        );
+
+        fs::write(dir.path().join("config.toml"), "database = \"config.db\"")?;
+        assert_eq!(
+            super::completion_database(completed, no_env),
+            PathBuf::from("config.db")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn candidates() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        write_fixture(&database)?;
+        Connection::open(&database)?.execute(
+            "INSERT INTO colors VALUES (72, 'Dark Bluish Gray', '6c6e68', 0, 0, 0, 2004, 2024)",
+            [],
+        )?;
+        let values = |kind, current: &str, escape| {
+            super::candidates(&database, kind, OsStr::new(current), escape)
+                .iter()
+                .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            values(IdentifierKind::Set, "100", false),
+            ["1000-1", "1001-1"]
+        );
+        assert_eq!(
+            values(IdentifierKind::Color, "dark b", false),
+            ["Dark Bluish Gray"]
+        );
+        // Words are passed as typed, and bash inserts candidates as is.
+        for current in ["Dark\\ B", "\"Dark B", "'Dark B"] {
+            assert_eq!(
+                values(IdentifierKind::Color, current, true),
+                ["Dark\\ Bluish\\ Gray"]
+            );
+        }
+        assert!(values(IdentifierKind::Set, "", false).len() <= super::MAX_CANDIDATES);
+        Ok(())
+    }
+
+    #[test]
+    fn shell_escape() {
+        assert_eq!(
+            super::shell_escape("[No Color/Any Color]"),
+            "\\[No\\ Color/Any\\ Color\\]"
+        );
+        assert_eq!(
+            super::unquote(&super::shell_escape("Trans-Clear (x)")),
+            "Trans-Clear (x)"
+        );
+    }
+
+    #[test]
+    fn generate_registration() -> anyhow::Result<()> {
+        for shell in [
+            clap_complete::Shell::Bash,
+            clap_complete::Shell::Elvish,
+            clap_complete::Shell::Fish,
+            clap_complete::Shell::PowerShell,
+            clap_complete::Shell::Zsh,
+        ] {
+            let mut script = Vec::new();
+            super::generate_registration(shell, &mut script)?;
+            let script = String::from_utf8(script)?;
+            assert!(script.contains("COMPLETE"), "{shell}: {script}");
+            assert!(script.contains("rbk-db"), "{shell}: {script}");
+        }
+        Ok(())
+    }
+}
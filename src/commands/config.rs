@@ -0,0 +1,130 @@
+use std::{env, fmt::Write as _};
+
+use clap::CommandFactory;
+
+use crate::config::Config;
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The config command to execute.
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Show the effective default of every argument, and where it comes from
+    /// (environment, configuration file or built-in default).
+    Show,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    match args.command {
+        Command::Show => run_show(),
+    }
+}
+
+fn run_show() -> anyhow::Result<()> {
+    let config = Config::load()?;
+    match config.path() {
+        Some(path) => println!("# configuration file: {}", path.display()),
+        None => println!("# no configuration file"),
+    }
+    let cmd = crate::Args::command();
+    // Catch invalid settings, which would make every other command fail.
+    config.apply(cmd.clone())?;
+    print!(
+        "{}",
+        render(&cmd, &config, |name| env::var(name).ok(), &mut Vec::new())
+    );
+    Ok(())
+}
+
+/// Renders the defaults of the arguments of a command and its subcommands, as
+/// TOML annotated with their sources.
+fn render<F>(cmd: &clap::Command, config: &Config, env: F, path: &mut Vec<String>) -> String
+where
+    F: Fn(&str) -> Option<String> + Copy,
+{
+    let path_refs: Vec<&str> = path.iter().map(String::as_str).collect();
+    let settings = config.settings(&path_refs);
+    let mut lines = String::new();
+    for arg in cmd.get_arguments() {
+        let id = arg.get_id().as_str();
+        if matches!(id, "help" | "version") || (!path.is_empty() && arg.is_global_set()) {
+            continue;
+        }
+        let env_name = arg.get_env().and_then(|name| name.to_str());
+        let (values, source) = match env_name.and_then(|name| Some((name, env(name)?))) {
+            Some((name, value)) => (vec![value], format!("env {name}")),
+            None => match settings.get(id) {
+                Some(values) => (values.clone(), "config".to_owned()),
+                None if arg.get_default_values().is_empty() => continue,
+                None => (
+                    arg.get_default_values()
+                        .iter()
+                        .map(|value| value.to_string_lossy().into_owned())
+                        .collect(),
+                    "default".to_owned(),
+                ),
+            },
+        };
+        let value = if arg.is_hide_env_values_set() && source != "default" {
+            "\"***\"".to_owned()
+        } else if matches!(arg.get_action(), clap::ArgAction::Append) {
+            let values: Vec<_> = values.iter().map(|value| literal(value)).collect();
+            format!("[{}]", values.join(", "))
+        } else {
+            values.iter().map(|value| literal(value)).collect()
+        };
+        let _ = writeln!(lines, "{id} = {value}  # {source}");
+    }
+
+    let mut out = String::new();
+    if !lines.is_empty() {
+        if !path.is_empty() {
+            let _ = writeln!(out, "\n[{}]", path.join("."));
+        }
+        out.push_str(&lines);
+    }
+    for sub in cmd.get_subcommands() {
+        path.push(sub.get_name().to_owned());
+        out.push_str(&render(sub, config, env, path));
+        path.pop();
+    }
+    out
+}
+
+/// Formats an argument value as a TOML literal.
+fn literal(value: &str) -> String {
+    if value == "true" || value == "false" || value.parse::<i64>().is_ok() {
+        value.to_owned()
+    } else {
+        toml::Value::String(value.to_owned()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use crate::config::Config;
+
+    #[test]
+    fn render() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "api_key = \"secret\"\n[dump]\nmax_concurrency = 8\n")?;
+        let config = Config::read(path)?;
+        let env = |name: &str| (name == "RBK_DB_DATABASE").then(|| "env.db".to_owned());
+        let out = super::render(&crate::Args::command(), &config, env, &mut Vec::new());
+
+        let dump = out.split("\n[dump]\n").nth(1).unwrap();
+        let dump = &dump[..dump.find("\n[").unwrap_or(dump.len())];
+        assert!(dump.contains("max_concurrency = 8  # config\n"));
+        assert!(dump.contains("timeout = 300  # default\n"));
+        assert!(dump.contains("database = \"env.db\"  # env RBK_DB_DATABASE\n"));
+        assert!(out.contains("\n[sync-user]\napi_key = \"***\"  # config\n"));
+        Ok(())
+    }
+}
@@ -1,49 +1,338 @@
+mod selection;
+mod summary;
+
 use std::{
-    fs::{self, File},
+    collections::BTreeSet,
+    fs, io,
     path::{Path, PathBuf},
-    time::SystemTime,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
 };
 
+use clap::builder::PossibleValuesParser;
+use url::Url;
+
 use crate::{
-    database::{Database, Insertable},
-    rebrickable::{Client, Table, table},
+    database::{
+        self, Database, FinalizeOptions, IndexProfile, Insertable, JournalMode, table_dependencies,
+    },
+    package,
+    part_graph::DEFAULT_EQUIVALENCE_TYPES,
+    progress::Progress,
+    rebrickable::{
+        Client, DEFAULT_CONNECT_TIMEOUT, DEFAULT_DOWNLOADS_URL, DEFAULT_MAX_CONCURRENCY,
+        DEFAULT_TIMEOUT, DownloadHandler, DownloadStats, Table, stream::RecordStream, table,
+    },
 };
 
-#[derive(Debug, clap::Parser)]
+use self::{
+    selection::select_tables,
+    summary::{InsertStats, Summary},
+};
+
+/// The tables of the dumps, in dependency order.
+const TABLES: [&str; 12] = [
+    table::Colors::NAME,
+    table::PartCategories::NAME,
+    table::Parts::NAME,
+    table::PartRelationships::NAME,
+    table::Elements::NAME,
+    table::Minifigs::NAME,
+    table::Themes::NAME,
+    table::Sets::NAME,
+    table::Inventories::NAME,
+    table::InventoryParts::NAME,
+    table::InventoryMinifigs::NAME,
+    table::InventorySets::NAME,
+];
+
+/// The archives of the tables, in the same order as `TABLES`.
+const FILENAMES: [&str; 12] = [
+    table::Colors::FILENAME,
+    table::PartCategories::FILENAME,
+    table::Parts::FILENAME,
+    table::PartRelationships::FILENAME,
+    table::Elements::FILENAME,
+    table::Minifigs::FILENAME,
+    table::Themes::FILENAME,
+    table::Sets::FILENAME,
+    table::Inventories::FILENAME,
+    table::InventoryParts::FILENAME,
+    table::InventoryMinifigs::FILENAME,
+    table::InventorySets::FILENAME,
+];
+
+#[derive(Clone, Debug, clap::Parser)]
 pub struct Args {
-    /// If the database file already exists, overwrite it.
+    /// If the database file already exists, replace it once the new one is
+    /// complete.
     #[arg(short, long)]
     force: bool,
+    /// The URL to download the table archives from.
+    #[arg(long, default_value = DEFAULT_DOWNLOADS_URL)]
+    downloads_url: Url,
+    /// The maximum number of tables downloaded concurrently.
+    #[arg(long, default_value_t = DEFAULT_MAX_CONCURRENCY)]
+    max_concurrency: usize,
+    /// The timeout to connect to the server, in seconds.
+    #[arg(long, default_value_t = DEFAULT_CONNECT_TIMEOUT.as_secs())]
+    connect_timeout: u64,
+    /// The timeout to receive data from the server, in seconds.
+    #[arg(long, default_value_t = DEFAULT_TIMEOUT.as_secs())]
+    timeout: u64,
+    /// Only dump these tables, and the tables they reference.
+    #[arg(long, value_delimiter = ',', value_parser = PossibleValuesParser::new(TABLES))]
+    tables: Option<Vec<String>>,
+    /// Do not dump these tables.
+    #[arg(long, value_delimiter = ',', value_parser = PossibleValuesParser::new(TABLES))]
+    exclude_tables: Vec<String>,
+    /// The number of invalid records to skip per table before giving up.
+    #[arg(long, default_value_t = 0)]
+    max_rejected_rows: u64,
+    /// The set of indexes to create.
+    #[arg(long, value_enum, default_value_t)]
+    index_profile: IndexProfile,
+    /// The journal mode of the finished database.
+    #[arg(long, value_enum, default_value_t)]
+    journal_mode: JournalMode,
+    /// The page size of the finished database, in bytes. Larger pages make
+    /// the file smaller and scans faster.
+    #[arg(long)]
+    page_size: Option<u32>,
+    /// Also write a package of the database to this file, to be installed
+    /// with `unpack`.
+    #[arg(long)]
+    package: Option<PathBuf>,
+    /// Write a JSON summary of the dump to this file, even if it fails.
+    #[arg(long)]
+    summary_json: Option<PathBuf>,
     /// The database file to create.
     #[arg(default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
     database: PathBuf,
+    /// The database to carry the tables filled by other commands over from,
+    /// when it is not the one being replaced.
+    #[arg(skip)]
+    carry_over_from: Option<PathBuf>,
+}
+
+impl Args {
+    pub(super) fn database(&self) -> &Path {
+        &self.database
+    }
+
+    /// Returns the same arguments, dumping to another file that takes over
+    /// the tables filled by other commands from the database.
+    pub(super) fn with_database(&self, database: PathBuf) -> Self {
+        Self {
+            force: true,
+            database,
+            carry_over_from: Some(self.database.clone()),
+            ..self.clone()
+        }
+    }
+
+    pub(super) fn client(&self) -> Client {
+        Client::new()
+            .with_downloads_url(self.downloads_url.clone())
+            .with_timeouts(
+                Duration::from_secs(self.connect_timeout),
+                Duration::from_secs(self.timeout),
+            )
+    }
+
+    fn tables(&self) -> anyhow::Result<BTreeSet<&'static str>> {
+        select_tables(
+            &TABLES,
+            self.tables.as_deref(),
+            &self.exclude_tables,
+            &table_dependencies()?,
+        )
+    }
+
+    /// Returns the archives of the tables to dump.
+    pub(super) fn archives(&self) -> anyhow::Result<Vec<&'static str>> {
+        let tables = self.tables()?;
+        Ok(TABLES
+            .iter()
+            .zip(FILENAMES)
+            .filter(|(table, _)| tables.contains(*table))
+            .map(|(_, filename)| filename)
+            .collect())
+    }
 }
 
 pub async fn run(args: Args) -> anyhow::Result<()> {
-    let db_path = args.database.as_path();
-    if db_path.exists() {
-        if args.force {
-            tracing::warn!("overwriting existing database at {}", db_path.display());
-            fs::remove_file(db_path)?;
-        } else {
-            anyhow::bail!("database already exists at {}", db_path.display());
+    let start = Instant::now();
+    let summary_json = args.summary_json.clone();
+    let mut downloads = Vec::new();
+    let inserts = Arc::new(Mutex::new(Vec::new()));
+    let result = dump(args, &mut downloads, &inserts).await;
+    if let Some(path) = summary_json {
+        let inserts = inserts.lock().unwrap();
+        let summary = Summary::new(&downloads, &inserts, start.elapsed(), &result);
+        if let Err(err) = summary.write(&path) {
+            tracing::error!("failed to write summary to {}: {err:#}", path.display());
+            result?;
+            return Err(err);
         }
     }
+    result
+}
 
-    let temp_dir = tempfile::tempdir()?;
-    let temp_dir = temp_dir.path();
+async fn dump(
+    args: Args,
+    download_stats: &mut Vec<DownloadStats>,
+    insert_stats: &Arc<Mutex<Vec<InsertStats>>>,
+) -> anyhow::Result<()> {
+    let tables = args.tables()?;
+    let db_path = args.database.clone();
+    if db_path.exists() && !args.force {
+        anyhow::bail!("database already exists at {}", db_path.display());
+    }
+    // The database is built next to the existing one, which is only replaced
+    // once the build succeeds.
+    let mut build_path = db_path.clone().into_os_string();
+    build_path.push(".tmp");
+    let build_path = PathBuf::from(build_path);
+    if build_path.exists() {
+        fs::remove_file(&build_path)?;
+    }
+    let previous = args
+        .carry_over_from
+        .as_deref()
+        .unwrap_or(&db_path)
+        .to_owned();
+    let previous = previous.exists().then_some(previous.as_path());
+    let result = build(
+        &args,
+        &tables,
+        &build_path,
+        previous,
+        download_stats,
+        insert_stats,
+    )
+    .await
+    // Checked last, as readers may open the database during the build.
+    .and_then(|()| database::ensure_replaceable(&db_path));
+    if let Err(err) = result {
+        if let Err(err) = fs::remove_file(&build_path)
+            && err.kind() != io::ErrorKind::NotFound
+        {
+            tracing::warn!("failed to remove {}: {err}", build_path.display());
+        }
+        return Err(err);
+    }
+    if db_path.exists() {
+        tracing::warn!("overwriting existing database at {}", db_path.display());
+    }
+    fs::rename(&build_path, &db_path)?;
 
-    let client = Client::new();
+    if let Some(package) = &args.package {
+        tracing::info!("writing package to {}", package.display());
+        let manifest = package::create(&db_path, package)?;
+        tracing::info!("packaged database with SHA-256 {}", manifest.sha256);
+    }
+    Ok(())
+}
 
-    client
-        .download_tables(temp_dir, current_timestamp()?)
-        .execute()
-        .await?;
+/// Downloads and loads the tables into a new database at `path`, carrying the
+/// tables filled by other commands over from the `previous` database if any.
+async fn build(
+    args: &Args,
+    tables: &BTreeSet<&'static str>,
+    path: &Path,
+    previous: Option<&Path>,
+    download_stats: &mut Vec<DownloadStats>,
+    insert_stats: &Arc<Mutex<Vec<InsertStats>>>,
+) -> anyhow::Result<()> {
+    let timestamp = current_timestamp()?;
+    let client = args.client();
+    let mut downloads = client
+        .download_tables(timestamp)
+        .with_max_concurrency(args.max_concurrency)
+        .with_max_rejected(args.max_rejected_rows);
+
+    // Tables are loaded in dependency order, so that foreign keys always
+    // reference rows that are already inserted. Downloads are started in the
+    // same order.
+    let colors = stream::<table::Colors>(&mut downloads, tables);
+    let part_categories = stream::<table::PartCategories>(&mut downloads, tables);
+    let parts = stream::<table::Parts>(&mut downloads, tables);
+    let part_relationships = stream::<table::PartRelationships>(&mut downloads, tables);
+    let elements = stream::<table::Elements>(&mut downloads, tables);
+    let minifigs = stream::<table::Minifigs>(&mut downloads, tables);
+    let themes = stream::<table::Themes>(&mut downloads, tables);
+    let sets = stream::<table::Sets>(&mut downloads, tables);
+    let inventories = stream::<table::Inventories>(&mut downloads, tables);
+    let inventory_parts = stream::<table::InventoryParts>(&mut downloads, tables);
+    let inventory_minifigs = stream::<table::InventoryMinifigs>(&mut downloads, tables);
+    let inventory_sets = stream::<table::InventorySets>(&mut downloads, tables);
+
+    let inserts = Arc::clone(insert_stats);
+    let writer_path = path.to_owned();
+    let writer = tokio::task::spawn_blocking(move || -> anyhow::Result<Database> {
+        let mut db = Database::open(&writer_path)?;
+        copy_table::<table::Colors>(&mut db, colors, &inserts)?;
+        copy_table::<table::PartCategories>(&mut db, part_categories, &inserts)?;
+        copy_table::<table::Parts>(&mut db, parts, &inserts)?;
+        copy_table::<table::PartRelationships>(&mut db, part_relationships, &inserts)?;
+        copy_table::<table::Elements>(&mut db, elements, &inserts)?;
+        copy_table::<table::Minifigs>(&mut db, minifigs, &inserts)?;
+        copy_table::<table::Themes>(&mut db, themes, &inserts)?;
+        copy_table::<table::Sets>(&mut db, sets, &inserts)?;
+        copy_table::<table::Inventories>(&mut db, inventories, &inserts)?;
+        copy_table::<table::InventoryParts>(&mut db, inventory_parts, &inserts)?;
+        copy_table::<table::InventoryMinifigs>(&mut db, inventory_minifigs, &inserts)?;
+        copy_table::<table::InventorySets>(&mut db, inventory_sets, &inserts)?;
+        Ok(db)
+    });
+
+    // Download errors take precedence, as they are the root cause of the
+    // parsing errors they trigger.
+    let (downloaded, written) = tokio::join!(downloads.join(), writer);
+    *download_stats = downloads.stats();
+    downloaded?;
+    let mut db = written??;
+
+    if let Some(previous) = previous {
+        for (table, carried) in db.carry_over(previous)? {
+            tracing::info!(
+                "carried {} rows of table {table} over from {}",
+                carried.rows,
+                previous.display()
+            );
+            if carried.dropped > 0 {
+                tracing::warn!(
+                    "dropped {} rows of table {table} referencing records that were removed",
+                    carried.dropped
+                );
+            }
+        }
+    }
 
-    let mut db = Database::open(db_path)?;
-    copy_tables(temp_dir, &mut db)?;
-    tracing::info!("creating indexes");
-    db.create_indexes()?;
+    if tables.contains(table::Parts::NAME) {
+        tracing::info!("computing part families");
+        db.create_part_families(DEFAULT_EQUIVALENCE_TYPES)?;
+    }
+    tracing::info!("creating {} indexes", args.index_profile.as_str());
+    db.create_indexes(args.index_profile)?;
+    tracing::info!("creating views");
+    db.create_views()?;
+    tracing::info!("optimizing database");
+    db.optimize()?;
+
+    tracing::info!("finalizing database");
+    db.set_metadata("rbk_db_version", env!("CARGO_PKG_VERSION"))?;
+    db.set_metadata("created_at", &timestamp.to_string())?;
+    db.set_metadata(
+        "tables",
+        &tables.iter().copied().collect::<Vec<_>>().join(","),
+    )?;
+    db.set_metadata("index_profile", args.index_profile.as_str())?;
+    db.finalize(FinalizeOptions {
+        journal_mode: args.journal_mode,
+        page_size: args.page_size,
+    })?;
     Ok(())
 }
 
@@ -53,36 +342,281 @@ fn current_timestamp() -> anyhow::Result<u64> {
         .as_secs())
 }
 
-fn copy_tables<P>(dump_dir: P, db: &mut Database) -> anyhow::Result<()>
+/// Starts downloading a table, if it is selected.
+fn stream<T>(
+    downloads: &mut DownloadHandler,
+    tables: &BTreeSet<&str>,
+) -> Option<RecordStream<T::Record>>
 where
-    P: AsRef<Path>,
+    T: Table,
+    T::Record: Send + 'static,
 {
-    let dump_dir = dump_dir.as_ref();
-    copy_table::<table::Colors, _>(dump_dir, db)?;
-    copy_table::<table::PartCategories, _>(dump_dir, db)?;
-    copy_table::<table::Parts, _>(dump_dir, db)?;
-    copy_table::<table::PartRelationships, _>(dump_dir, db)?;
-    copy_table::<table::Elements, _>(dump_dir, db)?;
-    copy_table::<table::Minifigs, _>(dump_dir, db)?;
-    copy_table::<table::Themes, _>(dump_dir, db)?;
-    copy_table::<table::Sets, _>(dump_dir, db)?;
-    copy_table::<table::Inventories, _>(dump_dir, db)?;
-    copy_table::<table::InventoryParts, _>(dump_dir, db)?;
-    copy_table::<table::InventoryMinifigs, _>(dump_dir, db)?;
-    copy_table::<table::InventorySets, _>(dump_dir, db)?;
-
-    Ok(())
+    tables.contains(T::NAME).then(|| downloads.stream::<T>())
 }
 
-fn copy_table<T, P>(dump_dir: P, db: &mut Database) -> anyhow::Result<()>
+fn copy_table<T>(
+    db: &mut Database,
+    records: Option<RecordStream<T::Record>>,
+    stats: &Mutex<Vec<InsertStats>>,
+) -> anyhow::Result<()>
 where
-    P: AsRef<Path>,
     T: Table,
     <T as Table>::Record: Insertable,
 {
-    let table_path = dump_dir.as_ref().join(T::FILENAME);
-    let table_file = File::open(table_path)?;
+    let Some(mut records) = records else {
+        return Ok(());
+    };
     tracing::info!("copying records to table {}", T::NAME);
-    db.insert_many(T::read_records_gz(table_file))?;
+    let start = Instant::now();
+    let mut progress = Progress::global().insert(T::NAME);
+    db.insert_many(records.by_ref().inspect(|_| progress.inc(1)))?;
+    drop(progress);
+    let elapsed = start.elapsed();
+    tracing::info!(
+        "copied {} records to table {} in {:.2}s ({:.0} records/s, {:.2}s waiting for records)",
+        records.received(),
+        T::NAME,
+        elapsed.as_secs_f64(),
+        records.received() as f64 / elapsed.as_secs_f64(),
+        records.waited().as_secs_f64(),
+    );
+    stats.lock().unwrap().push(InsertStats {
+        table: T::NAME,
+        rows: records.received() as u64,
+        duration: elapsed,
+    });
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rusqlite::Connection;
+    use tempfile::tempdir;
+
+    use super::Args;
+    use crate::{
+        database::{IndexProfile, JournalMode},
+        rebrickable::{DEFAULT_CONNECT_TIMEOUT, DEFAULT_MAX_CONCURRENCY, DEFAULT_TIMEOUT},
+        test_server::{TestResponse, TestServer},
+    };
+
+    #[tokio::test]
+    async fn dump() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route_tables()?;
+        let dir = tempdir()?;
+        let path = dir.path().join("rebrickable.db");
+
+        super::run(Args {
+            force: false,
+            downloads_url: server.url("/"),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT.as_secs(),
+            timeout: DEFAULT_TIMEOUT.as_secs(),
+            tables: None,
+            exclude_tables: Vec::new(),
+            max_rejected_rows: 0,
+            index_profile: IndexProfile::default(),
+            journal_mode: JournalMode::default(),
+            page_size: None,
+            package: Some(dir.path().join("rebrickable.tar.gz")),
+            summary_json: None,
+            database: path.clone(),
+            carry_over_from: None,
+        })
+        .await?;
+
+        let conn = Connection::open(&path)?;
+        let count = |table: &str| -> rusqlite::Result<u32> {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+        };
+        assert_eq!(count("colors")?, 4);
+        assert_eq!(count("themes")?, 2);
+        assert_eq!(count("inventory_parts")?, 4);
+        // The query planner has statistics about the tables.
+        assert!(count("sqlite_stat1")? > 0);
+        let index_profile: String = conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'index_profile'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(index_profile, "standard");
+        assert!(dir.path().join("rebrickable.tar.gz").exists());
+        assert_eq!(count("inventory_sets")?, 1);
+        assert_eq!(count("part_families")?, 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dump_tables() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route_tables()?;
+        let dir = tempdir()?;
+
+        super::run(Args {
+            force: false,
+            downloads_url: server.url("/"),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT.as_secs(),
+            timeout: DEFAULT_TIMEOUT.as_secs(),
+            tables: Some(vec!["elements".to_owned()]),
+            exclude_tables: Vec::new(),
+            max_rejected_rows: 0,
+            index_profile: IndexProfile::default(),
+            journal_mode: JournalMode::default(),
+            page_size: None,
+            package: None,
+            summary_json: None,
+            database: dir.path().join("rebrickable.db"),
+            carry_over_from: None,
+        })
+        .await?;
+
+        let mut downloaded: Vec<_> = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path.split_once('?').unwrap().0.to_owned())
+            .collect();
+        downloaded.sort();
+        assert_eq!(
+            downloaded,
+            [
+                "/colors.csv.gz",
+                "/elements.csv.gz",
+                "/part_categories.csv.gz",
+                "/parts.csv.gz",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dump_download_error() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        // Served before the fixture table registered for the same route.
+        server.route("/inventory_parts.csv.gz", TestResponse::new(500));
+        server.route_tables()?;
+        let dir = tempdir()?;
+        let summary_path = dir.path().join("summary.json");
+        let database = dir.path().join("rebrickable.db");
+        fs::write(&database, "previous")?;
+
+        let res = super::run(Args {
+            force: true,
+            downloads_url: server.url("/"),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT.as_secs(),
+            timeout: DEFAULT_TIMEOUT.as_secs(),
+            tables: None,
+            exclude_tables: Vec::new(),
+            max_rejected_rows: 0,
+            index_profile: IndexProfile::default(),
+            journal_mode: JournalMode::default(),
+            page_size: None,
+            package: None,
+            summary_json: Some(summary_path.clone()),
+            database: database.clone(),
+            carry_over_from: None,
+        })
+        .await;
+        assert!(res.is_err());
+        // The previous database is only replaced by a complete one.
+        assert_eq!(fs::read_to_string(&database)?, "previous");
+        assert!(!dir.path().join("rebrickable.db.tmp").exists());
+
+        let summary: serde_json::Value = serde_json::from_slice(&fs::read(&summary_path)?)?;
+        assert_eq!(summary["status"], "failed");
+        assert!(summary["error"].as_str().unwrap().contains("500"));
+        assert_eq!(summary["tables"].as_array().unwrap().len(), 12);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dump_in_use() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route_tables()?;
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        let args = || Args {
+            force: true,
+            downloads_url: server.url("/"),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT.as_secs(),
+            timeout: DEFAULT_TIMEOUT.as_secs(),
+            tables: Some(vec!["colors".to_owned()]),
+            exclude_tables: Vec::new(),
+            max_rejected_rows: 0,
+            index_profile: IndexProfile::default(),
+            journal_mode: JournalMode::Wal,
+            page_size: None,
+            package: None,
+            summary_json: None,
+            database: database.clone(),
+            carry_over_from: None,
+        };
+        super::run(args()).await?;
+
+        let reader = Connection::open(&database)?;
+        reader.query_row("SELECT COUNT(*) FROM colors", [], |_| Ok(()))?;
+        let previous = fs::read(&database)?;
+        let err = super::run(args()).await.unwrap_err();
+        assert!(err.to_string().contains("in use"), "{err}");
+        assert_eq!(fs::read(&database)?, previous);
+        assert!(!dir.path().join("rebrickable.db.tmp").exists());
+
+        drop(reader);
+        super::run(args()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dump_rejected_rows() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        let colors = "id,name,rgb,is_trans,num_parts,num_sets,y1,y2\n\
+            0,Black,05131D,False,1,1,1957,2024\n\
+            1,Invalid,not-a-colour,False,1,1,1957,2024\n\
+            4,Red,C91A09,False,1,1,1950,2024\n\
+            15,White,FFFFFF,False,1,1,1950,2024\n";
+        // Served to both runs, before the fixture table registered for the
+        // same route.
+        for _ in 0..2 {
+            server.route("/colors.csv.gz", TestResponse::gzipped(colors.as_bytes()));
+        }
+        server.route_tables()?;
+        let dir = tempdir()?;
+        let summary_path = dir.path().join("summary.json");
+        let args = |max_rejected_rows| Args {
+            force: true,
+            downloads_url: server.url("/"),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT.as_secs(),
+            timeout: DEFAULT_TIMEOUT.as_secs(),
+            tables: None,
+            exclude_tables: Vec::new(),
+            max_rejected_rows,
+            index_profile: IndexProfile::default(),
+            journal_mode: JournalMode::default(),
+            page_size: None,
+            package: None,
+            summary_json: Some(summary_path.clone()),
+            database: dir.path().join("rebrickable.db"),
+            carry_over_from: None,
+        };
+
+        assert!(super::run(args(0)).await.is_err());
+        super::run(args(1)).await?;
+
+        let summary: serde_json::Value = serde_json::from_slice(&fs::read(&summary_path)?)?;
+        assert_eq!(summary["status"], "succeeded");
+        let colors = &summary["tables"][0];
+        assert_eq!(colors["name"], "colors");
+        assert_eq!(colors["rows"], 3);
+        assert_eq!(colors["rejected_rows"], 1);
+        assert!(colors["download_bytes"].as_u64().unwrap() > 0);
+        Ok(())
+    }
+}
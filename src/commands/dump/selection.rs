@@ -0,0 +1,116 @@
+//! Selection of the tables to dump.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Returns the tables to dump, along with the tables they depend on through
+/// foreign keys.
+///
+/// Fails if a dependency of a selected table is explicitly excluded.
+pub fn select_tables(
+    all: &[&'static str],
+    tables: Option<&[String]>,
+    exclude: &[String],
+    dependencies: &BTreeMap<String, BTreeSet<String>>,
+) -> anyhow::Result<BTreeSet<&'static str>> {
+    let mut selected = BTreeSet::new();
+    for &table in all {
+        let is_requested = tables.is_none_or(|tables| tables.iter().any(|t| t == table));
+        let is_excluded = exclude.iter().any(|t| t == table);
+        if is_requested && is_excluded && tables.is_some() {
+            anyhow::bail!("table {table} is both requested and excluded");
+        }
+        if is_requested && !is_excluded {
+            selected.insert(table);
+        }
+    }
+
+    let mut closure = selected.clone();
+    let mut pending: Vec<_> = selected.iter().copied().collect();
+    while let Some(table) = pending.pop() {
+        for dependency in dependencies.get(table).into_iter().flatten() {
+            // Only the dumped tables matter.
+            let Some(&dependency) = all.iter().find(|&&t| t == dependency) else {
+                continue;
+            };
+            if closure.insert(dependency) {
+                pending.push(dependency);
+            }
+        }
+    }
+
+    for &table in closure.difference(&selected) {
+        let dependents = closure
+            .iter()
+            .filter(|dependent| {
+                dependencies
+                    .get(**dependent)
+                    .is_some_and(|d| d.contains(table))
+            })
+            .copied()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if exclude.iter().any(|t| t == table) {
+            anyhow::bail!("cannot exclude table {table}, which is required by {dependents}");
+        }
+        tracing::warn!("also dumping table {table}, which is required by {dependents}");
+    }
+    Ok(closure)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::select_tables;
+
+    const ALL: &[&str] = &["colors", "part_categories", "parts", "elements", "themes"];
+
+    fn dependencies() -> BTreeMap<String, BTreeSet<String>> {
+        let deps = |tables: &[&str]| tables.iter().map(|&t| t.to_owned()).collect();
+        BTreeMap::from([
+            ("colors".to_owned(), deps(&[])),
+            ("part_categories".to_owned(), deps(&[])),
+            ("parts".to_owned(), deps(&["part_categories"])),
+            ("elements".to_owned(), deps(&["parts", "colors"])),
+            ("themes".to_owned(), deps(&[])),
+            ("part_external_ids".to_owned(), deps(&["parts"])),
+        ])
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|&name| name.to_owned()).collect()
+    }
+
+    #[test]
+    fn closure() -> anyhow::Result<()> {
+        let tables = select_tables(ALL, Some(&names(&["elements"])), &[], &dependencies())?;
+        assert_eq!(
+            tables,
+            BTreeSet::from(["colors", "elements", "part_categories", "parts"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exclude() -> anyhow::Result<()> {
+        let tables = select_tables(ALL, None, &names(&["elements", "themes"]), &dependencies())?;
+        assert_eq!(
+            tables,
+            BTreeSet::from(["colors", "part_categories", "parts"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exclude_dependency() {
+        let res = select_tables(ALL, None, &names(&["parts"]), &dependencies());
+        assert!(res.is_err());
+        let res = select_tables(
+            ALL,
+            Some(&names(&["elements"])),
+            &names(&["colors"]),
+            &dependencies(),
+        );
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! A machine-readable summary of a dump.
+//!
+//! The format is versioned: fields may be added, but existing fields are not
+//! renamed or removed without bumping `version`.
+
+use std::{fs, path::Path, time::Duration};
+
+use serde::Serialize;
+
+use crate::rebrickable::DownloadStats;
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    version: u32,
+    status: Status,
+    /// The error that made the dump fail, if any.
+    error: Option<String>,
+    elapsed_secs: f64,
+    tables: Vec<TableSummary>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+struct TableSummary {
+    name: &'static str,
+    download_bytes: u64,
+    download_secs: f64,
+    /// The number of rows inserted, which is zero if the insertion failed.
+    rows: u64,
+    rejected_rows: u64,
+    insert_secs: f64,
+}
+
+/// Statistics about the insertion of a table.
+#[derive(Copy, Clone, Debug)]
+pub struct InsertStats {
+    pub table: &'static str,
+    pub rows: u64,
+    pub duration: Duration,
+}
+
+impl Summary {
+    pub fn new(
+        downloads: &[DownloadStats],
+        inserts: &[InsertStats],
+        elapsed: Duration,
+        result: &anyhow::Result<()>,
+    ) -> Self {
+        let tables = downloads
+            .iter()
+            .map(|download| {
+                let insert = inserts.iter().find(|insert| insert.table == download.table);
+                TableSummary {
+                    name: download.table,
+                    download_bytes: download.bytes,
+                    download_secs: download.duration.as_secs_f64(),
+                    rows: insert.map_or(0, |insert| insert.rows),
+                    rejected_rows: download.rejected,
+                    insert_secs: insert.map_or(0.0, |insert| insert.duration.as_secs_f64()),
+                }
+            })
+            .collect();
+        Self {
+            version: VERSION,
+            status: if result.is_ok() {
+                Status::Succeeded
+            } else {
+                Status::Failed
+            },
+            error: result.as_ref().err().map(|err| format!("{err:#}")),
+            elapsed_secs: elapsed.as_secs_f64(),
+            tables,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    database::{Additions, Database},
+    site::escape,
+};
+
+const REBRICKABLE_URL: &str = "https://rebrickable.com";
+
+#[derive(Debug, clap::Parser)]
+#[command(group(
+    clap::ArgGroup::new("outputs")
+        .args(["atom", "rss", "json"])
+        .required(true)
+        .multiple(true)
+))]
+pub struct Args {
+    /// The database to compare with. Defaults to the build preceding the
+    /// current one, as kept by `watch`.
+    #[arg(long)]
+    previous: Option<PathBuf>,
+    /// The state directory of `watch`, to find the previous build in.
+    /// Defaults to the database path with a `.builds` suffix.
+    #[arg(long)]
+    state_dir: Option<PathBuf>,
+    /// Write an Atom feed of the additions to this file.
+    #[arg(long)]
+    atom: Option<PathBuf>,
+    /// Write an RSS feed of the additions to this file.
+    #[arg(long)]
+    rss: Option<PathBuf>,
+    /// Write the additions as JSON to this file.
+    #[arg(long)]
+    json: Option<PathBuf>,
+    /// The title of the feeds.
+    #[arg(long, default_value = "New on Rebrickable")]
+    title: String,
+    /// The URL the feeds are published at, used as their identifier.
+    #[arg(long)]
+    feed_url: Option<Url>,
+    /// The database file to query.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let previous = match &args.previous {
+        Some(previous) => previous.clone(),
+        None => super::watch::previous_build(&args.database, args.state_dir.as_deref())?
+            .ok_or_else(|| anyhow::anyhow!("no previous build of {}", args.database.display()))?,
+    };
+    if !previous.exists() {
+        anyhow::bail!("previous database {} does not exist", previous.display());
+    }
+
+    let db = Database::open_read_only(&args.database)?;
+    let additions = db.additions(&previous)?;
+    tracing::info!(
+        "found {} new sets, {} new minifigs and {} new themes",
+        additions.sets.len(),
+        additions.minifigs.len(),
+        additions.themes.len()
+    );
+    let created_at = match created_at(&db)? {
+        Some(created_at) => created_at,
+        None => SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs(),
+    };
+    let feed = Feed {
+        title: &args.title,
+        feed_url: args.feed_url.as_ref(),
+        created_at,
+        previous_created_at: created_at_of(&previous)?,
+        additions: &additions,
+    };
+
+    if let Some(path) = &args.atom {
+        write(path, &feed.atom())?;
+    }
+    if let Some(path) = &args.rss {
+        write(path, &feed.rss())?;
+    }
+    if let Some(path) = &args.json {
+        write(path, &serde_json::to_string_pretty(&feed)?)?;
+    }
+    Ok(())
+}
+
+fn created_at(db: &Database) -> anyhow::Result<Option<u64>> {
+    Ok(db
+        .metadata()?
+        .get("created_at")
+        .and_then(|created_at| created_at.parse().ok()))
+}
+
+fn created_at_of(path: &Path) -> anyhow::Result<Option<u64>> {
+    created_at(&Database::open_read_only(path)?)
+}
+
+fn write(path: &Path, content: &str) -> anyhow::Result<()> {
+    fs::write(path, content)
+        .map_err(|err| anyhow::anyhow!("failed to write {}: {err}", path.display()))?;
+    tracing::info!("wrote {}", path.display());
+    Ok(())
+}
+
+/// The additions between two builds, serialized as the JSON output.
+#[derive(Debug, Serialize)]
+struct Feed<'a> {
+    #[serde(skip)]
+    title: &'a str,
+    #[serde(skip)]
+    feed_url: Option<&'a Url>,
+    /// When the current build was created, in seconds since the Unix epoch.
+    created_at: u64,
+    /// When the previous build was created, if recorded.
+    previous_created_at: Option<u64>,
+    #[serde(flatten)]
+    additions: &'a Additions,
+}
+
+/// An addition, as shown in the feeds.
+struct Entry {
+    link: String,
+    title: String,
+    summary: String,
+    img_url: Option<String>,
+}
+
+impl Feed<'_> {
+    fn entries(&self) -> Vec<Entry> {
+        let themes = self.additions.themes.iter().map(|theme| Entry {
+            link: format!("{REBRICKABLE_URL}/sets/?theme={}", theme.id),
+            title: format!("New theme: {}", theme.name),
+            summary: theme.path.join(" > "),
+            img_url: None,
+        });
+        let sets = self.additions.sets.iter().map(|set| Entry {
+            link: format!("{REBRICKABLE_URL}/sets/{}/", set.set_num),
+            title: format!("New set {}: {}", set.set_num, set.name),
+            summary: format!(
+                "{}, {}, {} parts",
+                set.theme_path.join(" > "),
+                set.year,
+                set.num_parts
+            ),
+            img_url: Some(set.img_url.clone()).filter(|url| !url.is_empty()),
+        });
+        let minifigs = self.additions.minifigs.iter().map(|fig| Entry {
+            link: format!("{REBRICKABLE_URL}/minifigs/{}/", fig.fig_num),
+            title: format!("New minifig {}: {}", fig.fig_num, fig.name),
+            summary: format!("{} parts", fig.num_parts),
+            img_url: Some(fig.img_url.clone()).filter(|url| !url.is_empty()),
+        });
+        themes.chain(sets).chain(minifigs).collect()
+    }
+
+    fn atom(&self) -> String {
+        let updated = rfc3339(self.created_at);
+        let id = self.feed_url.map_or("urn:rbk-db:additions", Url::as_str);
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        let _ = writeln!(out, "  <title>{}</title>", escape(self.title));
+        let _ = writeln!(out, "  <id>{}</id>", escape(id));
+        if let Some(url) = self.feed_url {
+            let _ = writeln!(
+                out,
+                "  <link rel=\"self\" href=\"{}\"/>",
+                escape(url.as_str())
+            );
+        }
+        let _ = writeln!(out, "  <updated>{updated}</updated>");
+        out.push_str("  <author><name>rbk-db</name></author>\n");
+        for entry in self.entries() {
+            out.push_str("  <entry>\n");
+            let _ = writeln!(out, "    <title>{}</title>", escape(&entry.title));
+            let _ = writeln!(out, "    <id>{}</id>", escape(&entry.link));
+            let _ = writeln!(out, "    <link href=\"{}\"/>", escape(&entry.link));
+            if let Some(img_url) = &entry.img_url {
+                let _ = writeln!(
+                    out,
+                    "    <link rel=\"enclosure\" type=\"image/jpeg\" href=\"{}\"/>",
+                    escape(img_url)
+                );
+            }
+            let _ = writeln!(out, "    <updated>{updated}</updated>");
+            let _ = writeln!(out, "    <summary>{}</summary>", escape(&entry.summary));
+            out.push_str("  </entry>\n");
+        }
+        out.push_str("</feed>\n");
+        out
+    }
+
+    fn rss(&self) -> String {
+        let pub_date = rfc2822(self.created_at);
+        let link = self.feed_url.map_or(REBRICKABLE_URL, Url::as_str);
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        out.push_str("<rss version=\"2.0\">\n  <channel>\n");
+        let _ = writeln!(out, "    <title>{}</title>", escape(self.title));
+        let _ = writeln!(out, "    <link>{}</link>", escape(link));
+        let _ = writeln!(out, "    <description>{}</description>", escape(self.title));
+        let _ = writeln!(out, "    <lastBuildDate>{pub_date}</lastBuildDate>");
+        for entry in self.entries() {
+            out.push_str("    <item>\n");
+            let _ = writeln!(out, "      <title>{}</title>", escape(&entry.title));
+            let _ = writeln!(out, "      <link>{}</link>", escape(&entry.link));
+            let _ = writeln!(out, "      <guid>{}</guid>", escape(&entry.link));
+            let _ = writeln!(
+                out,
+                "      <description>{}</description>",
+                escape(&entry.summary)
+            );
+            if let Some(img_url) = &entry.img_url {
+                let _ = writeln!(
+                    out,
+                    "      <enclosure url=\"{}\" length=\"0\" type=\"image/jpeg\"/>",
+                    escape(img_url)
+                );
+            }
+            let _ = writeln!(out, "      <pubDate>{pub_date}</pubDate>");
+            out.push_str("    </item>\n");
+        }
+        out.push_str("  </channel>\n</rss>\n");
+        out
+    }
+}
+
+/// Splits seconds since the Unix epoch into the days since the epoch, the
+/// civil date and the time of day, in UTC.
+fn civil(secs: u64) -> (u64, (i64, u64, u64), (u64, u64, u64)) {
+    let days = secs / 86400;
+    let time = secs % 86400;
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = (yoe + era * 400) as i64 + i64::from(month <= 2);
+    (
+        days,
+        (year, month, day),
+        (time / 3600, time / 60 % 60, time % 60),
+    )
+}
+
+fn rfc3339(secs: u64) -> String {
+    let (_, (year, month, day), (hour, minute, second)) = civil(secs);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+fn rfc2822(secs: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (days, (year, month, day), (hour, minute, second)) = civil(secs);
+    format!(
+        "{}, {day:02} {} {year:04} {hour:02}:{minute:02}:{second:02} +0000",
+        WEEKDAYS[(days % 7) as usize],
+        MONTHS[month as usize - 1]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::{
+        Additions,
+        additions::{NewSet, NewTheme},
+    };
+
+    use super::Feed;
+
+    #[test]
+    fn dates() {
+        assert_eq!(super::rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(super::rfc3339(1_709_210_096), "2024-02-29T12:34:56Z");
+        assert_eq!(
+            super::rfc2822(1_709_210_096),
+            "Thu, 29 Feb 2024 12:34:56 +0000"
+        );
+    }
+
+    #[test]
+    fn feeds() -> anyhow::Result<()> {
+        let additions = Additions {
+            sets: vec![NewSet {
+                set_num: "1001-1".to_owned(),
+                name: "Fire Cart & Hose".to_owned(),
+                year: 2020,
+                num_parts: 3,
+                theme_path: vec!["Town".to_owned(), "Fire".to_owned()],
+                img_url: "https://cdn.rebrickable.com/media/sets/1001-1.jpg".to_owned(),
+            }],
+            minifigs: Vec::new(),
+            themes: vec![NewTheme {
+                id: 3,
+                name: "Space".to_owned(),
+                path: vec!["Space".to_owned()],
+            }],
+        };
+        let feed = Feed {
+            title: "New on Rebrickable",
+            feed_url: None,
+            created_at: 1_709_210_096,
+            previous_created_at: Some(1_709_123_696),
+            additions: &additions,
+        };
+
+        let atom = feed.atom();
+        assert!(atom.contains("<title>New set 1001-1: Fire Cart &amp; Hose</title>"));
+        assert!(atom.contains("<summary>Town &gt; Fire, 2020, 3 parts</summary>"));
+        assert!(atom.contains("<updated>2024-02-29T12:34:56Z</updated>"));
+        assert!(atom.contains(
+            "<link rel=\"enclosure\" type=\"image/jpeg\" \
+             href=\"https://cdn.rebrickable.com/media/sets/1001-1.jpg\"/>"
+        ));
+        let rss = feed.rss();
+        assert!(rss.contains("<guid>https://rebrickable.com/sets/?theme=3</guid>"));
+        assert!(rss.contains("<pubDate>Thu, 29 Feb 2024 12:34:56 +0000</pubDate>"));
+
+        let json: serde_json::Value = serde_json::from_str(&serde_json::to_string(&feed)?)?;
+        assert_eq!(json["previous_created_at"], 1_709_123_696);
+        assert_eq!(json["sets"][0]["theme_path"][1], "Fire");
+        assert_eq!(json["themes"][0]["id"], 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::{convert::Infallible, path::PathBuf};
+
+use url::Url;
+
+use crate::{
+    database::Database,
+    rebrickable::{Client, api},
+};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The Rebrickable API key.
+    #[arg(long, env = "RBK_DB_API_KEY", hide_env_values = true)]
+    api_key: String,
+    /// The base URL of the Rebrickable API.
+    #[arg(long, default_value = api::DEFAULT_BASE_URL)]
+    api_url: Url,
+    /// The database file to update.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    if !args.database.exists() {
+        anyhow::bail!("database does not exist at {}", args.database.display());
+    }
+    let api = Client::new().api(args.api_key).with_base_url(args.api_url);
+
+    tracing::info!("fetching colors from the API");
+    let colors = api.colors().await?;
+    tracing::info!("fetching parts from the API");
+    let parts = api.parts().await?;
+
+    let mut db = Database::open_for_update(&args.database)?;
+    tracing::info!("copying records to table color_external_ids");
+    db.insert_many(
+        colors
+            .iter()
+            .flat_map(|color| color.external_ids())
+            .map(Ok::<_, Infallible>),
+    )?;
+    tracing::info!("copying records to table part_external_ids");
+    db.insert_many(
+        parts
+            .iter()
+            .flat_map(|part| part.external_ids())
+            .map(Ok::<_, Infallible>),
+    )?;
+    Ok(())
+}
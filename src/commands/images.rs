@@ -0,0 +1,256 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use tokio::{sync::Semaphore, task::JoinSet};
+use url::Url;
+
+use crate::{
+    database::{CachedImage, Database, ImageFilter, ImageKind},
+    images::{ImageCache, StoredImage},
+    rebrickable::{
+        Client, DEFAULT_MAX_CONCURRENCY, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_DELAY,
+        list::SetListEntry,
+    },
+    types::InventoryVersion,
+};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The images command to execute.
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Download the images of sets, minifigures and parts into the local
+    /// cache, recording them in the `images` table.
+    Fetch(FetchArgs),
+}
+
+#[derive(Debug, clap::Parser)]
+struct FetchArgs {
+    /// Only fetch the images of sets of this theme, including its
+    /// sub-themes. May be repeated.
+    #[arg(long = "theme", value_name = "ID")]
+    themes: Vec<i32>,
+    /// Only fetch the images of sets released this year. May be repeated.
+    #[arg(long = "year", value_name = "YEAR")]
+    years: Vec<i32>,
+    /// Only fetch the images of the sets of a set list exported from
+    /// Rebrickable.
+    #[arg(long)]
+    sets: Option<PathBuf>,
+    /// The versions of the inventories of the sets to fetch minifigure and
+    /// part images from: `latest`, `all` or a version number.
+    #[arg(long, default_value_t)]
+    inventory_version: InventoryVersion,
+    /// The kinds of images to fetch. Defaults to all of them.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    kinds: Vec<ImageKind>,
+    /// Fetch images again, even if they are already cached.
+    #[arg(long)]
+    refresh: bool,
+    /// The maximum number of concurrent downloads.
+    #[arg(long, default_value_t = DEFAULT_MAX_CONCURRENCY)]
+    max_concurrency: usize,
+    /// The number of times a failed download is retried.
+    #[arg(long, default_value_t = DEFAULT_MAX_RETRIES)]
+    retries: u32,
+    /// The directory of the image cache. Defaults to
+    /// `$XDG_CACHE_HOME/rbk-db/images`.
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+    /// The database file to update.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    match args.command {
+        Command::Fetch(args) => {
+            let client = Client::new().with_retries(args.retries, DEFAULT_RETRY_DELAY);
+            let cache_dir = match &args.cache_dir {
+                Some(cache_dir) => cache_dir.clone(),
+                None => ImageCache::default_dir().ok_or_else(|| {
+                    anyhow::anyhow!("no cache directory, set --cache-dir or $XDG_CACHE_HOME")
+                })?,
+            };
+            run_fetch(&args, client, ImageCache::new(cache_dir)).await
+        }
+    }
+}
+
+async fn run_fetch(args: &FetchArgs, client: Client, cache: ImageCache) -> anyhow::Result<()> {
+    if !args.database.exists() {
+        anyhow::bail!("database does not exist at {}", args.database.display());
+    }
+    let db = Database::open_for_update(&args.database)?;
+    let set_nums = match &args.sets {
+        Some(path) => SetListEntry::read_all(File::open(path)?)?
+            .into_iter()
+            .map(|entry| entry.set_num)
+            .collect(),
+        None => Vec::new(),
+    };
+    let filter = ImageFilter {
+        themes: args.themes.clone(),
+        years: args.years.clone(),
+        set_nums,
+        kinds: args.kinds.clone(),
+        inventory_version: args.inventory_version,
+    };
+
+    let cached = db.cached_images()?;
+    let urls = db.image_urls(&filter)?;
+    let total = urls.len();
+    let urls: Vec<String> = urls
+        .into_iter()
+        .filter(|url| {
+            args.refresh
+                || !cached
+                    .get(url)
+                    .is_some_and(|image| Path::new(&image.path).exists())
+        })
+        .collect();
+    tracing::info!(
+        "fetching {} images into {} ({} already cached)",
+        urls.len(),
+        cache.dir().display(),
+        total - urls.len()
+    );
+
+    let semaphore = Arc::new(Semaphore::new(args.max_concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut failed = 0;
+    let mut record = |(url, res): (String, anyhow::Result<StoredImage>)| -> anyhow::Result<()> {
+        match res {
+            Ok(image) => db.record_image(&CachedImage {
+                url,
+                sha256: image.sha256,
+                path: image.path.to_string_lossy().into_owned(),
+                size: image.size as i64,
+                fetched_at: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs() as i64,
+            }),
+            Err(err) => {
+                tracing::warn!("failed to fetch {url}: {err:#}");
+                failed += 1;
+                Ok(())
+            }
+        }
+    };
+    for url in &urls {
+        // Record finished downloads while waiting for a slot.
+        let permit = Arc::clone(&semaphore).acquire_owned().await?;
+        while let Some(res) = tasks.try_join_next() {
+            record(res?)?;
+        }
+        let (url, client, cache) = (url.clone(), client.clone(), cache.clone());
+        tasks.spawn(async move {
+            let _permit = permit;
+            let res = fetch_image(&url, &client, cache).await;
+            (url, res)
+        });
+    }
+    while let Some(res) = tasks.join_next().await {
+        record(res?)?;
+    }
+
+    if failed > 0 {
+        anyhow::bail!("failed to fetch {failed} of {} images", urls.len());
+    }
+    Ok(())
+}
+
+async fn fetch_image(url: &str, client: &Client, cache: ImageCache) -> anyhow::Result<StoredImage> {
+    let url = Url::parse(url)?;
+    let content = client.fetch(url.clone()).await?;
+    let image = tokio::task::spawn_blocking(move || cache.store(&url, &content)).await??;
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use clap::Parser;
+    use rusqlite::Connection;
+    use tempfile::tempdir;
+
+    use super::FetchArgs;
+    use crate::{
+        database::Database,
+        images::ImageCache,
+        rebrickable::Client,
+        test_server::{TestResponse, TestServer},
+    };
+
+    #[tokio::test]
+    async fn fetch() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route("/media/sets/1000-1.jpg", TestResponse::new(503));
+        server.route(
+            "/media/sets/1000-1.jpg",
+            TestResponse::new(200).with_body("set"),
+        );
+        // Identical images are cached once.
+        server.route(
+            "/media/sets/1001-1.jpg",
+            TestResponse::new(200).with_body("set"),
+        );
+        server.route(
+            "/media/sets/fig-000001.jpg",
+            TestResponse::new(200).with_body("minifig"),
+        );
+
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        drop(Database::open(&database)?);
+        let conn = Connection::open(&database)?;
+        conn.execute_batch(include_str!("../database/fixture.sql"))?;
+        conn.execute(
+            "UPDATE sets SET img_url = replace(img_url, 'https://cdn.rebrickable.com/', ?1)",
+            [server.url("/").as_str()],
+        )?;
+        conn.execute(
+            "UPDATE minifigs SET img_url = replace(img_url, 'https://cdn.rebrickable.com/', ?1)",
+            [server.url("/").as_str()],
+        )?;
+        let cache_dir = dir.path().join("cache");
+        let args = |args: &[&str]| {
+            let mut all = vec!["fetch", "--database", database.to_str().unwrap()];
+            all.extend_from_slice(args);
+            FetchArgs::try_parse_from(all)
+        };
+        let client = Client::new().with_retries(1, Duration::from_millis(10));
+        let cache = ImageCache::new(&cache_dir);
+
+        // The part image of the fixture is not served by the test server.
+        let err = super::run_fetch(&args(&[])?, client.clone(), cache.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "failed to fetch 1 of 4 images");
+        let count = |sql| conn.query_row(sql, [], |row| row.get::<_, i64>(0));
+        assert_eq!(count("SELECT COUNT(*) FROM images")?, 3);
+        assert_eq!(count("SELECT COUNT(DISTINCT path) FROM images")?, 2);
+
+        // Cached images are not fetched again.
+        let requests = server.requests().len();
+        super::run_fetch(&args(&["--kinds", "set,minifig"])?, client, cache).await?;
+        assert_eq!(server.requests().len(), requests);
+
+        let path: String = conn.query_row(
+            "SELECT path FROM images WHERE url LIKE '%/1000-1.jpg'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(fs::read(path)?, b"set");
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    ldraw::{self, Mapping, Model},
+    rebrickable::list::PartListEntry,
+};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The format of the file to import.
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Compute the parts list of an LDraw model (`.ldr` or `.mpd`).
+    Ldraw(LdrawArgs),
+}
+
+#[derive(Debug, clap::Parser)]
+struct LdrawArgs {
+    /// A CSV file mapping LDraw identifiers to Rebrickable ones, with columns
+    /// `kind` (`part` or `color`), `ldraw_id` and `rebrickable_id`.
+    #[arg(short, long)]
+    mapping: Option<PathBuf>,
+    /// The file to write the parts list to, in Rebrickable's CSV format.
+    /// Defaults to the standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// The LDraw model file.
+    model: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    match args.command {
+        Command::Ldraw(args) => run_ldraw(args),
+    }
+}
+
+fn run_ldraw(args: LdrawArgs) -> anyhow::Result<()> {
+    let mapping = match &args.mapping {
+        Some(path) => Mapping::read_csv(File::open(path)?)?,
+        None => Mapping::default(),
+    };
+    let model = Model::open(&args.model)?;
+
+    let mut parts: BTreeMap<(String, i32), u32> = BTreeMap::new();
+    for ((ldraw_id, ldraw_colour), quantity) in model.parts()? {
+        if ldraw_colour == ldraw::MAIN_COLOUR {
+            tracing::warn!("skipping {quantity} part(s) {ldraw_id} with no colour");
+            continue;
+        }
+        let Some(color_id) = mapping.color_id(ldraw_colour) else {
+            tracing::warn!(
+                "skipping {quantity} part(s) {ldraw_id} with unmapped colour {ldraw_colour}"
+            );
+            continue;
+        };
+        let part_num = mapping.part_num(&ldraw_id).to_owned();
+        *parts.entry((part_num, color_id)).or_default() += quantity;
+    }
+
+    let entries: Vec<_> = parts
+        .into_iter()
+        .map(|((part_num, color_id), quantity)| PartListEntry {
+            part_num,
+            color_id,
+            quantity,
+        })
+        .collect();
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    PartListEntry::write_all(output, &entries)?;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use std::{fs, io, path::PathBuf};
+
+use rustyline::{
+    Context, Editor,
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+};
+
+use crate::{
+    database::Database,
+    shell::{self, Flow, Shell},
+};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The file to keep the history of inputs in. Defaults to
+    /// `$XDG_STATE_HOME/rbk-db/history`.
+    #[arg(long)]
+    history: Option<PathBuf>,
+    /// The database file to query.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let mut shell = Shell::new(Database::open_read_only(&args.database)?);
+    let mut editor = Editor::<Helper, DefaultHistory>::new()?;
+    editor.set_helper(Some(Helper(shell::Completer::new()?)));
+    let history = args.history.or_else(shell::default_history_path);
+    if let Some(history) = &history
+        && history.exists()
+    {
+        editor.load_history(history)?;
+    }
+
+    eprintln!("Enter SQL statements ending with \";\", or \".help\" for the shortcuts.");
+    let mut stdout = io::stdout().lock();
+    loop {
+        let input = match editor.readline("rbk-db> ") {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+        editor.add_history_entry(input.as_str())?;
+        match shell.execute(&input, &mut stdout) {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Quit) => break,
+            Err(err) => eprintln!("error: {err:#}"),
+        }
+    }
+
+    if let Some(history) = &history {
+        if let Some(dir) = history.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        editor.save_history(history)?;
+    }
+    Ok(())
+}
+
+/// Completes names, and keeps reading lines until an SQL statement ends.
+struct Helper(shell::Completer);
+
+impl rustyline::Helper for Helper {}
+
+impl Completer for Helper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.0.complete(line, pos))
+    }
+}
+
+impl Hinter for Helper {
+    type Hint = String;
+}
+
+impl Highlighter for Helper {}
+
+impl Validator for Helper {
+    fn validate(&self, ctx: &mut ValidationContext<'_>) -> rustyline::Result<ValidationResult> {
+        Ok(if shell::is_complete(ctx.input()) {
+            ValidationResult::Valid(None)
+        } else {
+            ValidationResult::Incomplete
+        })
+    }
+}
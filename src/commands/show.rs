@@ -0,0 +1,55 @@
+use std::{io, path::PathBuf};
+
+use clap_complete::ArgValueCompleter;
+
+use super::completion;
+use crate::{database::Database, shell::Shell, types::InventoryVersion};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The kind of item to show.
+    #[command(subcommand)]
+    command: Command,
+    /// The database file to query.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Show a set and the parts of its inventory.
+    Set {
+        /// The versions of the inventory to show: `latest`, `all` or a
+        /// version number.
+        #[arg(long, default_value_t)]
+        inventory_version: InventoryVersion,
+        /// The set number, with or without its version.
+        #[arg(add = ArgValueCompleter::new(completion::set_nums))]
+        set_num: String,
+    },
+    /// Show a part and the colours it appears in.
+    Part {
+        /// The part number.
+        #[arg(add = ArgValueCompleter::new(completion::part_nums))]
+        part_num: String,
+    },
+    /// Show the colours matching a name, or an id.
+    Color {
+        /// The colour name, as a `LIKE` pattern, or id.
+        #[arg(add = ArgValueCompleter::new(completion::color_names))]
+        name: String,
+    },
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let mut shell = Shell::new(Database::open_read_only(&args.database)?);
+    let mut stdout = io::stdout().lock();
+    match &args.command {
+        Command::Set {
+            inventory_version,
+            set_num,
+        } => shell.show_set(set_num, *inventory_version, &mut stdout),
+        Command::Part { part_num } => shell.show_part(part_num, &mut stdout),
+        Command::Color { name } => shell.show_color(name, &mut stdout),
+    }
+}
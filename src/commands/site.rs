@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use crate::{database::Database, site, types::InventoryVersion};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The directory to write the site to.
+    #[arg(short, long, default_value = "site")]
+    output: PathBuf,
+    /// The versions of the inventories of the sets to show: `latest`, `all`
+    /// or a version number.
+    #[arg(long, default_value_t)]
+    inventory_version: InventoryVersion,
+    /// The title of the site.
+    #[arg(long, default_value = "LEGO catalogue")]
+    title: String,
+    /// The database file to query.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let db = Database::open_read_only(&args.database)?;
+    tracing::info!("loading the catalogue");
+    let catalog = db.catalog(args.inventory_version)?;
+    tracing::info!("generating the site into {}", args.output.display());
+    let stats = site::generate(&catalog, &args.title, &args.output)?;
+    tracing::info!(
+        "wrote {} pages and copied {} cached images",
+        stats.pages,
+        stats.images
+    );
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use crate::{database::Database, part_graph::DEFAULT_EQUIVALENCE_TYPES, types::PartRelationType};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The relationship types that make parts interchangeable.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_values_t = DEFAULT_EQUIVALENCE_TYPES.to_vec(),
+    )]
+    rel_types: Vec<PartRelationType>,
+    /// The database file to query.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+    /// The part number to look up.
+    part_num: String,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let db = Database::open_read_only(&args.database)?;
+    if db.part_name(&args.part_num)?.is_none() {
+        anyhow::bail!("unknown part {}", args.part_num);
+    }
+    let graph = db.part_graph()?;
+
+    let print_part = |part_num: &str| -> anyhow::Result<()> {
+        let name = db.part_name(part_num)?.unwrap_or_default();
+        println!("  {part_num}\t{name}");
+        Ok(())
+    };
+
+    let mut substitutes = graph.equivalents(&args.part_num, &args.rel_types);
+    substitutes.remove(&args.part_num);
+    if !substitutes.is_empty() {
+        println!("Substitutes:");
+        for part_num in substitutes {
+            print_part(&part_num)?;
+        }
+    }
+    if let Some(base) = graph.unprinted_base(&args.part_num) {
+        println!("Unprinted base:");
+        print_part(base)?;
+    }
+    let subparts = graph.subparts(&args.part_num);
+    if !subparts.is_empty() {
+        println!("Sub-parts:");
+        for part_num in subparts {
+            print_part(&part_num)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use url::Url;
+
+use crate::{
+    database::Database,
+    rebrickable::{Client, api},
+};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The Rebrickable API key.
+    #[arg(long, env = "RBK_DB_API_KEY", hide_env_values = true)]
+    api_key: String,
+    /// The token of the Rebrickable user whose lists to synchronize.
+    #[arg(long, env = "RBK_DB_USER_TOKEN", hide_env_values = true)]
+    user_token: String,
+    /// The base URL of the Rebrickable API.
+    #[arg(long, default_value = api::DEFAULT_BASE_URL)]
+    api_url: Url,
+    /// The database file to update.
+    #[arg(long, default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    if !args.database.exists() {
+        anyhow::bail!("database does not exist at {}", args.database.display());
+    }
+    let api = Client::new().api(args.api_key).with_base_url(args.api_url);
+
+    tracing::info!("fetching user lists from the API");
+    let lists = api.user_lists(&args.user_token).await?;
+
+    let mut db = Database::open_for_update(&args.database)?;
+    for (table, stats) in db.sync_user_lists(&lists)? {
+        tracing::info!(
+            "synchronized table {table}: {} rows, {} added, {} removed",
+            stats.rows,
+            stats.added,
+            stats.removed,
+        );
+        if stats.skipped > 0 {
+            tracing::warn!(
+                "skipped {} entries of table {table} not matching the catalogue",
+                stats.skipped,
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::{fs::File, path::PathBuf};
+
+use url::Url;
+
+use crate::{
+    package::{self, Manifest},
+    rebrickable::{CHUNKS_CAPACITY, Client, stream::ChannelReader},
+};
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// If the database file already exists, overwrite it.
+    #[arg(short, long)]
+    force: bool,
+    /// The package to install, as a file or an HTTP(S) URL.
+    package: String,
+    /// The database file to create.
+    #[arg(default_value = "rebrickable.db", env = "RBK_DB_DATABASE")]
+    database: PathBuf,
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    if args.database.exists() && !args.force {
+        anyhow::bail!("database already exists at {}", args.database.display());
+    }
+
+    let manifest = match Url::parse(&args.package) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => fetch(url, args.database).await?,
+        _ => {
+            let package = File::open(&args.package)?;
+            tokio::task::spawn_blocking(move || package::unpack(package, &args.database)).await??
+        }
+    };
+    tracing::info!(
+        "installed database of {} bytes, created by rbk-db {} with tables {}",
+        manifest.size,
+        manifest
+            .metadata
+            .get("rbk_db_version")
+            .map_or("?", String::as_str),
+        manifest.metadata.get("tables").map_or("?", String::as_str),
+    );
+    Ok(())
+}
+
+/// Downloads a package and unpacks it on the fly.
+async fn fetch(url: Url, database: PathBuf) -> anyhow::Result<Manifest> {
+    let (tx, rx) = tokio::sync::mpsc::channel(CHUNKS_CAPACITY);
+    let unpacked =
+        tokio::task::spawn_blocking(move || package::unpack(ChannelReader::new(rx), &database));
+    tracing::info!("downloading package from {url}");
+    let downloaded = Client::new().download("package", url, &tx).await;
+    if let Err(err) = &downloaded {
+        // Make sure the package is not taken for a complete one.
+        let _ = tx.send(Err(std::io::Error::other(err.to_string()))).await;
+    }
+    drop(tx);
+    // Download errors take precedence, as they are the root cause of the
+    // unpacking errors they trigger.
+    let unpacked = unpacked.await?;
+    downloaded?;
+    unpacked
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::Args;
+    use crate::{
+        database::{Database, FinalizeOptions},
+        package,
+        test_server::{TestResponse, TestServer},
+    };
+
+    #[tokio::test]
+    async fn fetch_package() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let database = dir.path().join("source.db");
+        Database::open(&database)?.finalize(FinalizeOptions::default())?;
+        let package = dir.path().join("rebrickable.tar.gz");
+        package::create(&database, &package)?;
+
+        let server = TestServer::start().await?;
+        server.route(
+            "/rebrickable.tar.gz",
+            TestResponse::new(200).with_body(fs::read(&package)?),
+        );
+        server.route("/missing.tar.gz", TestResponse::new(404));
+        let installed = dir.path().join("rebrickable.db");
+
+        super::run(Args {
+            force: false,
+            package: server.url("/rebrickable.tar.gz").to_string(),
+            database: installed.clone(),
+        })
+        .await?;
+        assert_eq!(fs::read(&installed)?, fs::read(&database)?);
+
+        let res = super::run(Args {
+            force: true,
+            package: server.url("/missing.tar.gz").to_string(),
+            database: installed.clone(),
+        })
+        .await;
+        assert!(res.unwrap_err().to_string().contains("404"));
+        // The installed database is left untouched.
+        assert_eq!(fs::read(&installed)?, fs::read(&database)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+mod status;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use self::status::{Build, State, Status};
+use super::dump;
+use crate::database;
+
+const STATUS_FILE: &str = "status.json";
+
+#[derive(Debug, clap::Parser)]
+#[group(id = "watch")]
+pub struct Args {
+    /// The time between checks for new archives, in seconds.
+    #[arg(long, default_value_t = 3600)]
+    interval: u64,
+    /// The number of builds to keep for rollback, including the current one.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    keep: u32,
+    /// The directory holding the builds and the status file. Defaults to the
+    /// database path with a `.builds` suffix.
+    #[arg(long)]
+    state_dir: Option<PathBuf>,
+    /// Check once, rebuilding if needed, then exit.
+    #[arg(long)]
+    once: bool,
+    /// Switch the database back to the previous build, then exit.
+    #[arg(long, conflicts_with = "once")]
+    rollback: bool,
+    #[command(flatten)]
+    dump: dump::Args,
+}
+
+/// A source of time, injected so that the watcher can be tested without
+/// waiting.
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
+
+pub async fn run(args: Args) -> anyhow::Result<()> {
+    let watcher = Watcher::new(&args)?;
+    if args.rollback {
+        watcher.rollback()
+    } else {
+        watcher.watch(&SystemClock, args.once.then_some(1)).await
+    }
+}
+
+/// Rebuilds the database whenever upstream archives change.
+///
+/// Each build is dumped to a new file in the state directory, then copied
+/// over the database and renamed, so that readers never see a partial
+/// database and a failed build leaves the current one in place.
+///
+/// A database in WAL mode is only switched while it is not in use.
+#[derive(Debug)]
+struct Watcher<'a> {
+    args: &'a Args,
+    state_dir: PathBuf,
+    status: Status,
+}
+
+impl<'a> Watcher<'a> {
+    fn new(args: &'a Args) -> anyhow::Result<Self> {
+        let state_dir = state_dir(args.dump.database(), args.state_dir.as_deref());
+        fs::create_dir_all(&state_dir)?;
+        let status = Status::read(&state_dir.join(STATUS_FILE))?;
+        Ok(Self {
+            args,
+            state_dir,
+            status,
+        })
+    }
+
+    /// Checks the archives every interval, stopping after the given number of
+    /// checks if any.
+    ///
+    /// Failed checks are reported in the status file, and only stop the
+    /// watcher if they are the last ones.
+    async fn watch<C>(mut self, clock: &C, checks: Option<usize>) -> anyhow::Result<()>
+    where
+        C: Clock,
+    {
+        let interval = Duration::from_secs(self.args.interval);
+        let mut count = 0;
+        loop {
+            let res = self.check(clock).await;
+            count += 1;
+            let is_last = checks.is_some_and(|checks| count >= checks);
+            self.status.next_check = (!is_last).then(|| unix_secs(clock.now() + interval));
+            if let Err(err) = &res {
+                tracing::error!("{err:#}");
+                self.status.state = State::Failed;
+                self.status.last_error = Some(format!("{err:#}"));
+            }
+            self.write_status()?;
+            if is_last {
+                return res;
+            }
+            clock.sleep(interval).await;
+        }
+    }
+
+    async fn check<C>(&mut self, clock: &C) -> anyhow::Result<()>
+    where
+        C: Clock,
+    {
+        let now = unix_secs(clock.now());
+        self.status.last_check = Some(now);
+        self.set_state(State::Checking)?;
+        let client = self.args.dump.client();
+        let mut fingerprints = BTreeMap::new();
+        for filename in self.args.dump.archives()? {
+            let fingerprint = client.archive_fingerprint(filename, now).await?;
+            fingerprints.insert(filename.to_owned(), fingerprint);
+        }
+        if fingerprints == self.status.fingerprints && self.args.dump.database().exists() {
+            tracing::info!("archives are unchanged");
+            self.status.state = State::Idle;
+            self.status.last_error = None;
+            return Ok(());
+        }
+
+        tracing::info!("archives changed, rebuilding the database");
+        self.set_state(State::Building)?;
+        let file = format!("rebrickable-{now}.db");
+        let build_path = self.state_dir.join(&file);
+        if let Err(err) = dump::run(self.args.dump.with_database(build_path.clone())).await {
+            remove_if_exists(&build_path)?;
+            return Err(err.context("failed to build the database"));
+        }
+        if let Err(err) = self.switch_to(&build_path) {
+            remove_if_exists(&build_path)?;
+            return Err(err);
+        }
+        self.status.builds.push(Build {
+            file,
+            created_at: unix_secs(clock.now()),
+        });
+        self.status.fingerprints = fingerprints;
+        while self.status.builds.len() > self.args.keep as usize {
+            let build = self.status.builds.remove(0);
+            remove_if_exists(&self.state_dir.join(&build.file))?;
+        }
+        self.status.state = State::Idle;
+        self.status.last_error = None;
+        Ok(())
+    }
+
+    /// Switches the database back to the previous build, removing the
+    /// current one.
+    ///
+    /// The fingerprints are kept, so that the archives the removed build was
+    /// made from are not rebuilt until they change again.
+    fn rollback(mut self) -> anyhow::Result<()> {
+        let [.., previous, _] = self.status.builds.as_slice() else {
+            anyhow::bail!("there is no previous build to roll back to");
+        };
+        self.switch_to(&self.state_dir.join(&previous.file))?;
+        let current = self.status.builds.pop().expect("there are two builds");
+        remove_if_exists(&self.state_dir.join(&current.file))?;
+        self.write_status()
+    }
+
+    fn switch_to(&self, build_path: &Path) -> anyhow::Result<()> {
+        let database = self.args.dump.database();
+        database::ensure_replaceable(database)?;
+        let tmp_path = with_suffix(database, ".tmp");
+        fs::copy(build_path, &tmp_path)?;
+        fs::rename(&tmp_path, database)?;
+        tracing::info!(
+            "switched database {} to build {}",
+            database.display(),
+            build_path.display()
+        );
+        Ok(())
+    }
+
+    fn set_state(&mut self, state: State) -> anyhow::Result<()> {
+        self.status.state = state;
+        self.write_status()
+    }
+
+    fn write_status(&self) -> anyhow::Result<()> {
+        self.status.write(&self.state_dir.join(STATUS_FILE))
+    }
+}
+
+/// Returns the path of the build preceding the current one, if any.
+pub(super) fn previous_build(
+    database: &Path,
+    state_dir: Option<&Path>,
+) -> anyhow::Result<Option<PathBuf>> {
+    let state_dir = self::state_dir(database, state_dir);
+    let status = Status::read(&state_dir.join(STATUS_FILE))?;
+    Ok(match status.builds.as_slice() {
+        [.., previous, _] => Some(state_dir.join(&previous.file)),
+        _ => None,
+    })
+}
+
+fn state_dir(database: &Path, state_dir: Option<&Path>) -> PathBuf {
+    state_dir.map_or_else(|| with_suffix(database, ".builds"), Path::to_owned)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        future::{self, Future},
+        path::Path,
+        sync::Mutex,
+        time::{Duration, SystemTime},
+    };
+
+    use clap::Parser;
+    use rusqlite::Connection;
+    use tempfile::tempdir;
+
+    use super::{
+        Args, Clock, Watcher,
+        status::{State, Status},
+    };
+    use crate::{
+        database::{CachedImage, Database},
+        test_server::{TestResponse, TestServer},
+    };
+
+    /// A clock whose time only passes when sleeping, instantly.
+    struct FakeClock(Mutex<SystemTime>);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> SystemTime {
+            *self.0.lock().unwrap()
+        }
+
+        fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+            *self.0.lock().unwrap() += duration;
+            future::ready(())
+        }
+    }
+
+    fn downloads(server: &TestServer, table: &str) -> usize {
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.method == "GET" && request.path.starts_with(table))
+            .count()
+    }
+
+    fn builds(dir: &Path) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for entry in fs::read_dir(dir)? {
+            if entry?.path().extension().is_some_and(|ext| ext == "db") {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    #[tokio::test]
+    async fn watch() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route_tables()?;
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        let state_dir = dir.path().join("builds");
+        let args = Args::try_parse_from([
+            "watch",
+            "--keep",
+            "2",
+            "--downloads-url",
+            server.url("/").as_str(),
+            "--state-dir",
+            state_dir.to_str().unwrap(),
+            database.to_str().unwrap(),
+        ])?;
+        let clock = FakeClock(Mutex::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1000),
+        ));
+        let status = || Status::read(&state_dir.join("status.json"));
+
+        // The first check builds the database, the second one finds nothing
+        // new.
+        Watcher::new(&args)?.watch(&clock, Some(2)).await?;
+        assert!(database.exists());
+        assert_eq!(downloads(&server, "/colors"), 1);
+        let first = status()?;
+        assert_eq!(first.state, State::Idle);
+        assert_eq!(first.last_check, Some(4600));
+        assert_eq!(first.next_check, None);
+        assert_eq!(first.builds.len(), 1);
+
+        // Rebuilds carry over the tables filled by other commands.
+        let image = CachedImage {
+            url: "https://cdn.rebrickable.com/media/sets/1000-1.jpg".to_owned(),
+            sha256: "00".repeat(32),
+            path: "/cache/00.jpg".to_owned(),
+            size: 42,
+            fetched_at: 1000,
+        };
+        Database::open_for_update(&database)?.record_image(&image)?;
+
+        // Changed archives trigger rebuilds, keeping two builds.
+        let colors = fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("src/rebrickable/fixtures/colors.csv"),
+        )?;
+        for version in ["v2", "v3"] {
+            clock.sleep(Duration::from_secs(3600)).await;
+            server.reroute(
+                "/colors.csv.gz",
+                TestResponse::gzipped(&colors).with_header("ETag", version),
+            );
+            Watcher::new(&args)?.watch(&clock, Some(1)).await?;
+        }
+        assert_eq!(downloads(&server, "/colors"), 3);
+        assert_eq!(
+            Database::open_read_only(&database)?.cached_images()?[&image.url],
+            image
+        );
+        assert_eq!(status()?.builds.len(), 2);
+        assert_eq!(builds(&state_dir)?, 2);
+
+        // A failed check leaves the database in place.
+        server.reroute("/colors.csv.gz", TestResponse::new(500));
+        let res = Watcher::new(&args)?.watch(&clock, Some(1)).await;
+        assert!(res.is_err());
+        assert!(database.exists());
+        let failed = status()?;
+        assert_eq!(failed.state, State::Failed);
+        assert!(failed.last_error.unwrap().contains("500"));
+
+        // Rolling back removes the current build.
+        let previous = failed.builds[0].clone();
+        Watcher::new(&args)?.rollback()?;
+        assert_eq!(status()?.builds, std::slice::from_ref(&previous));
+        assert_eq!(
+            fs::read(&database)?,
+            fs::read(state_dir.join(&previous.file))?
+        );
+        assert!(Watcher::new(&args)?.rollback().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wal() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route_tables()?;
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        let state_dir = dir.path().join("builds");
+        let args = Args::try_parse_from([
+            "watch",
+            "--journal-mode",
+            "wal",
+            "--tables",
+            "colors",
+            "--downloads-url",
+            server.url("/").as_str(),
+            "--state-dir",
+            state_dir.to_str().unwrap(),
+            database.to_str().unwrap(),
+        ])?;
+        let clock = FakeClock(Mutex::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1000),
+        ));
+        Watcher::new(&args)?.watch(&clock, Some(1)).await?;
+
+        // The database is not switched while in use.
+        let reader = Connection::open(&database)?;
+        reader.query_row("SELECT COUNT(*) FROM colors", [], |_| Ok(()))?;
+        let previous = fs::read(&database)?;
+        let colors = fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("src/rebrickable/fixtures/colors.csv"),
+        )?;
+        server.reroute(
+            "/colors.csv.gz",
+            TestResponse::gzipped(&colors).with_header("ETag", "v2"),
+        );
+        clock.sleep(Duration::from_secs(3600)).await;
+        let err = Watcher::new(&args)?
+            .watch(&clock, Some(1))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("in use"), "{err}");
+        assert_eq!(fs::read(&database)?, previous);
+        assert_eq!(builds(&state_dir)?, 1);
+
+        drop(reader);
+        Watcher::new(&args)?.watch(&clock, Some(1)).await?;
+        assert_eq!(builds(&state_dir)?, 2);
+        Ok(())
+    }
+}
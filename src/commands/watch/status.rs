@@ -0,0 +1,84 @@
+//! The state of the watcher, persisted between runs and readable by
+//! monitoring tools.
+//!
+//! The format is versioned: fields may be added, but existing fields are not
+//! renamed or removed without bumping `version`.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+const VERSION: u32 = 1;
+
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Status {
+    pub version: u32,
+    pub state: State,
+    /// When upstream archives were last checked, in seconds since the Unix
+    /// epoch.
+    pub last_check: Option<u64>,
+    /// When upstream archives will next be checked, if the watcher is
+    /// running.
+    pub next_check: Option<u64>,
+    /// The error that made the last check or build fail, if any.
+    pub last_error: Option<String>,
+    /// The fingerprints of the archives the current build was made from, by
+    /// file name.
+    pub fingerprints: BTreeMap<String, String>,
+    /// The kept builds, from the oldest to the current one.
+    pub builds: Vec<Build>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Idle,
+    Checking,
+    Building,
+    Failed,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Build {
+    /// The file name of the build, in the builds directory.
+    pub file: String,
+    /// When the build completed, in seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            state: State::Idle,
+            last_check: None,
+            next_check: None,
+            last_error: None,
+            fingerprints: BTreeMap::new(),
+            builds: Vec::new(),
+        }
+    }
+}
+
+impl Status {
+    /// Reads the status file, if it exists.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let status: Self = serde_json::from_slice(&fs::read(path)?)?;
+        if status.version != VERSION {
+            anyhow::bail!("unsupported status file version {}", status.version);
+        }
+        Ok(status)
+    }
+
+    /// Writes the status file, replacing it atomically.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,475 @@
+//! Configuration file, providing defaults for command-line arguments.
+//!
+//! Settings are named after the arguments they provide a default for.
+//! Top-level settings apply to every command accepting the argument, while
+//! tables apply to a command and its subcommands:
+//!
+//! ```toml
+//! database = "/data/rebrickable.db"
+//! api_key = "..."
+//!
+//! [dump]
+//! max_concurrency = 8
+//! exclude_tables = ["inventory_sets"]
+//!
+//! [collection.parts]
+//! exclude_spares = true
+//! ```
+//!
+//! Arguments given on the command line or through environment variables take
+//! precedence over the file.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{ArgAction, ArgMatches, Command};
+
+use crate::dirs;
+
+/// The environment variable overriding the path of the configuration file.
+pub const CONFIG_ENV: &str = "RBK_DB_CONFIG";
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    path: Option<PathBuf>,
+    settings: toml::Table,
+}
+
+impl Config {
+    /// Loads the file given by `--config` or `RBK_DB_CONFIG`, or else
+    /// `$XDG_CONFIG_HOME/rbk-db/config.toml` if it exists.
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_from(env::args_os())
+    }
+
+    /// Same as [`Config::load`], with the given command-line arguments.
+    pub fn load_from<I>(args: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = OsString>,
+    {
+        let explicit_path = raw_arg(args, "--config").or_else(|| env::var_os(CONFIG_ENV));
+        match explicit_path {
+            Some(path) => Self::read(PathBuf::from(path)),
+            None => match default_path() {
+                Some(path) if path.exists() => Self::read(path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    pub(crate) fn read(path: PathBuf) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(&path).map_err(|err| {
+            anyhow::anyhow!("failed to read configuration {}: {err}", path.display())
+        })?;
+        let settings = content.parse().map_err(|err| {
+            anyhow::anyhow!("failed to parse configuration {}: {err}", path.display())
+        })?;
+        Ok(Self {
+            path: Some(path),
+            settings,
+        })
+    }
+
+    /// The path of the loaded file, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Sets the settings as the default values of the arguments of a command
+    /// and its subcommands.
+    ///
+    /// Fails on settings that do not match any argument.
+    pub fn apply(&self, cmd: Command) -> anyhow::Result<Command> {
+        let (cmd, invalid) = self.apply_valid(cmd);
+        if !invalid.0.is_empty() {
+            let errors: Vec<_> = invalid.0.into_iter().map(|(_, err)| err).collect();
+            anyhow::bail!("invalid configuration: {}", errors.join("; "));
+        }
+        Ok(cmd)
+    }
+
+    /// Same as [`Config::apply`], returning the invalid settings instead of
+    /// failing, so that they only fail the commands using them.
+    ///
+    /// Boolean flags set by the file can be turned off with `--flag=false`.
+    pub fn apply_valid(&self, cmd: Command) -> (Command, InvalidSettings) {
+        let mut invalid = InvalidSettings::default();
+        let cmd = apply_table(cmd, &self.settings, &BTreeMap::new(), "", &mut invalid);
+        (cmd, invalid)
+    }
+
+    /// Returns the values set by the file for the arguments of a command,
+    /// given by its path from the root command.
+    pub fn settings(&self, path: &[&str]) -> BTreeMap<String, Vec<String>> {
+        let mut settings = BTreeMap::new();
+        let mut table = Some(&self.settings);
+        let mut names = path.iter();
+        while let Some(current) = table {
+            for (key, value) in current {
+                if let Ok(values) = values(value) {
+                    settings.insert(key.clone(), values);
+                }
+            }
+            table = names
+                .next()
+                .and_then(|name| current.get(*name))
+                .and_then(toml::Value::as_table);
+        }
+        settings
+    }
+}
+
+/// Settings that could not be applied, along with the table of the command
+/// they come from.
+#[derive(Clone, Debug, Default)]
+pub struct InvalidSettings(Vec<(String, String)>);
+
+impl InvalidSettings {
+    /// Fails on the invalid settings of the invoked command or of its parent
+    /// commands, and returns the others as warnings.
+    ///
+    /// Top-level settings that match no argument are not used by any
+    /// command, so they only trigger warnings.
+    pub fn check(self, matches: &ArgMatches) -> anyhow::Result<Vec<String>> {
+        let mut invoked = String::new();
+        let mut current = matches;
+        while let Some((name, sub)) = current.subcommand() {
+            invoked.push_str(name);
+            invoked.push('.');
+            current = sub;
+        }
+        let (errors, warnings): (Vec<_>, Vec<_>) = self
+            .0
+            .into_iter()
+            .partition(|(table, _)| !table.is_empty() && invoked.starts_with(table.as_str()));
+        if !errors.is_empty() {
+            let errors: Vec<_> = errors.into_iter().map(|(_, err)| err).collect();
+            anyhow::bail!("invalid configuration: {}", errors.join("; "));
+        }
+        Ok(warnings.into_iter().map(|(_, warning)| warning).collect())
+    }
+}
+
+/// Returns the value of an option from the raw command-line arguments, as
+/// `--config` is needed before they are parsed.
+pub(crate) fn raw_arg<I>(args: I, name: &str) -> Option<OsString>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if arg == name {
+            return args.next();
+        } else if let Some(value) = arg
+            .to_str()
+            .and_then(|arg| arg.strip_prefix(name))
+            .and_then(|arg| arg.strip_prefix('='))
+        {
+            return Some(value.into());
+        }
+    }
+    None
+}
+
+fn default_path() -> Option<PathBuf> {
+    Some(dirs::xdg_dir("XDG_CONFIG_HOME", ".config")?.join("config.toml"))
+}
+
+/// Converts a setting to argument values.
+fn values(value: &toml::Value) -> Result<Vec<String>, &'static str> {
+    match value {
+        toml::Value::String(s) => Ok(vec![s.clone()]),
+        toml::Value::Integer(i) => Ok(vec![i.to_string()]),
+        toml::Value::Float(f) => Ok(vec![f.to_string()]),
+        toml::Value::Boolean(b) => Ok(vec![b.to_string()]),
+        toml::Value::Array(array) => array
+            .iter()
+            .map(|value| match values(value)?.as_slice() {
+                [value] => Ok(value.clone()),
+                _ => Err("nested arrays are not supported"),
+            })
+            .collect(),
+        toml::Value::Datetime(datetime) => Ok(vec![datetime.to_string()]),
+        toml::Value::Table(_) => Err("tables are not supported"),
+    }
+}
+
+fn apply_table(
+    mut cmd: Command,
+    table: &toml::Table,
+    inherited: &BTreeMap<String, Vec<String>>,
+    path: &str,
+    invalid: &mut InvalidSettings,
+) -> Command {
+    let mut error = |table: &str, err: String| invalid.0.push((table.to_owned(), err));
+    let mut settings = inherited.clone();
+    for (key, value) in table {
+        if !value.is_table() {
+            match values(value) {
+                Ok(values) => {
+                    settings.insert(key.clone(), values);
+                }
+                Err(err) => error(path, format!("{path}{key}: {err}")),
+            }
+        }
+    }
+
+    for (key, value) in table {
+        let setting = format!("{path}{key}");
+        if value.is_table() {
+            if cmd.find_subcommand(key).is_none() {
+                error(
+                    &format!("{setting}."),
+                    format!("{setting}: unknown command"),
+                );
+            }
+        } else if !accepts(&cmd, key) {
+            error(path, format!("{setting}: unknown setting"));
+        }
+    }
+
+    for (key, values) in &settings {
+        let Some(arg) = cmd.get_arguments().find(|arg| arg.get_id() == key) else {
+            continue;
+        };
+        let is_multiple = matches!(arg.get_action(), ArgAction::Append);
+        if values.len() != 1 && !is_multiple {
+            error(path, format!("{path}{key}: expected a single value"));
+            continue;
+        }
+        let is_flag = matches!(arg.get_action(), ArgAction::SetTrue);
+        // A default makes the argument optional.
+        let values = values.clone();
+        cmd = cmd.mut_arg(key, |arg| {
+            let arg = arg.default_values(values).required(false);
+            if is_flag {
+                // Otherwise, a flag set by the file could not be turned off.
+                arg.action(ArgAction::Set)
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_missing_value("true")
+            } else {
+                arg
+            }
+        });
+    }
+
+    let names: Vec<String> = cmd
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_owned())
+        .collect();
+    for name in names {
+        let empty = toml::Table::new();
+        let sub_table = table
+            .get(&name)
+            .and_then(toml::Value::as_table)
+            .unwrap_or(&empty);
+        let sub_path = format!("{path}{name}.");
+        cmd = cmd.mut_subcommand(&name, |sub| {
+            apply_table(sub, sub_table, &settings, &sub_path, invalid)
+        });
+    }
+    cmd
+}
+
+/// Returns whether a command or one of its subcommands accepts an argument.
+fn accepts(cmd: &Command, id: &str) -> bool {
+    cmd.get_arguments().any(|arg| arg.get_id() == id)
+        || cmd.get_subcommands().any(|sub| accepts(sub, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, path::PathBuf};
+
+    use clap::CommandFactory;
+
+    use super::Config;
+
+    fn config(content: &str) -> anyhow::Result<Config> {
+        Ok(Config {
+            path: None,
+            settings: content.parse()?,
+        })
+    }
+
+    fn parse(config: &Config, args: &[&str]) -> anyhow::Result<clap::ArgMatches> {
+        Ok(config
+            .apply(crate::Args::command())?
+            .try_get_matches_from(args)?)
+    }
+
+    #[test]
+    fn raw_arg() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            super::raw_arg(args(&["rbk-db", "--config", "a.toml", "dump"]), "--config"),
+            Some("a.toml".into())
+        );
+        assert_eq!(
+            super::raw_arg(args(&["rbk-db", "dump", "--config=b.toml"]), "--config"),
+            Some("b.toml".into())
+        );
+        assert_eq!(
+            super::raw_arg(args(&["rbk-db", "dump", "--", "--config"]), "--config"),
+            None
+        );
+        assert_eq!(
+            super::raw_arg(
+                args(&["rbk-db", "--config-dir=c", "--database=d.db"]),
+                "--config"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn apply() -> anyhow::Result<()> {
+        let config = config(
+            r#"
+            database = "global.db"
+            api_key = "secret"
+
+            [dump]
+            max_concurrency = 8
+            exclude_tables = ["inventory_sets", "inventory_minifigs"]
+
+            [collection.parts]
+            database = "collection.db"
+            exclude_spares = true
+            "#,
+        )?;
+
+        let matches = parse(&config, &["rbk-db", "dump"])?;
+        let dump = matches.subcommand_matches("dump").unwrap();
+        assert_eq!(
+            dump.get_one::<PathBuf>("database"),
+            Some(&PathBuf::from("global.db"))
+        );
+        assert_eq!(dump.get_one::<usize>("max_concurrency"), Some(&8));
+        assert_eq!(
+            dump.get_many::<String>("exclude_tables")
+                .unwrap()
+                .collect::<Vec<_>>(),
+            ["inventory_sets", "inventory_minifigs"]
+        );
+
+        // The command line takes precedence.
+        let matches = parse(&config, &["rbk-db", "dump", "--max-concurrency", "2"])?;
+        let dump = matches.subcommand_matches("dump").unwrap();
+        assert_eq!(dump.get_one::<usize>("max_concurrency"), Some(&2));
+
+        let matches = parse(&config, &["rbk-db", "collection", "parts", "sets.csv"])?;
+        let parts = matches
+            .subcommand_matches("collection")
+            .and_then(|matches| matches.subcommand_matches("parts"))
+            .unwrap();
+        assert_eq!(
+            parts.get_one::<PathBuf>("database"),
+            Some(&PathBuf::from("collection.db"))
+        );
+        assert_eq!(parts.get_one::<bool>("exclude_spares"), Some(&true));
+
+        // Required arguments are satisfied by the file.
+        let matches = parse(&config, &["rbk-db", "fetch-external-ids"])?;
+        let fetch = matches.subcommand_matches("fetch-external-ids").unwrap();
+        assert_eq!(
+            fetch.get_one::<String>("api_key").map(String::as_str),
+            Some("secret")
+        );
+
+        assert_eq!(
+            config.settings(&["collection", "parts"])["database"],
+            ["collection.db"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn apply_flag() -> anyhow::Result<()> {
+        let config = config("[collection.parts]\nexclude_spares = true")?;
+        let exclude_spares = |args: &[&str]| -> anyhow::Result<Option<bool>> {
+            let matches = parse(&config, args)?;
+            let (_, collection) = matches.subcommand().unwrap();
+            let (_, parts) = collection.subcommand().unwrap();
+            Ok(parts.get_one::<bool>("exclude_spares").copied())
+        };
+        let args = ["rbk-db", "collection", "parts", "sets.csv"];
+        assert_eq!(exclude_spares(&args)?, Some(true));
+        // Flags set by the file can be turned off.
+        assert_eq!(
+            exclude_spares(&[&args[..], &["--exclude-spares=false"]].concat())?,
+            Some(false)
+        );
+        assert_eq!(
+            exclude_spares(&[&args[..], &["--exclude-spares"]].concat())?,
+            Some(true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn apply_valid() -> anyhow::Result<()> {
+        let config = config(
+            r#"
+            colour = "red"
+
+            [dump]
+            max_concurrency = 8
+            tabels = ["colors"]
+
+            [undump]
+            force = true
+            "#,
+        )?;
+        let check = |args: &[&str]| {
+            let (cmd, invalid) = config.apply_valid(crate::Args::command());
+            invalid.check(&cmd.try_get_matches_from(args)?)
+        };
+
+        // Other commands only warn about the settings they do not use.
+        assert_eq!(
+            check(&["rbk-db", "completion", "bash"])?,
+            [
+                "colour: unknown setting",
+                "undump: unknown command",
+                "dump.tabels: unknown setting",
+            ]
+        );
+        let err = check(&["rbk-db", "dump"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration: dump.tabels: unknown setting"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn apply_invalid() -> anyhow::Result<()> {
+        let config = config(
+            r#"
+            colour = "red"
+
+            [dump]
+            max_concurrency = [1, 2]
+
+            [undump]
+            force = true
+            "#,
+        )?;
+        let err = config.apply(crate::Args::command()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration: colour: unknown setting; undump: unknown command; \
+             dump.max_concurrency: expected a single value"
+        );
+        Ok(())
+    }
+}
@@ -1,15 +1,64 @@
-use std::path::Path;
-
-use rusqlite::{CachedStatement, Connection, Transaction, params};
+pub(crate) mod additions;
+mod carry_over;
+mod catalog;
+mod check;
+mod collection;
+mod finalize;
+mod identifiers;
+mod images;
+mod part_graph;
+mod query;
+mod user;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use rusqlite::{Connection, OpenFlags, Statement, ToSql, Transaction, limits::Limit, params};
 use url::Url;
 
-use crate::{rebrickable::record, types::Rgb};
+pub use self::{
+    additions::Additions,
+    catalog::{Catalog, CatalogMinifig, CatalogPart, CatalogSet, CatalogTheme, InventoryPart},
+    check::Severity,
+    finalize::{FinalizeOptions, JournalMode, ensure_replaceable},
+    identifiers::IdentifierKind,
+    images::{CachedImage, ImageFilter, ImageKind},
+    query::{QueryResult, schema_columns},
+};
+use crate::{
+    rebrickable::{api, record},
+    types::{InventoryKind, InventoryVersion, Rgb},
+};
 
 #[derive(Debug)]
 pub struct Database {
     conn: Connection,
 }
 
+/// The set of indexes to create, each profile including the smaller ones.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, clap::ValueEnum)]
+pub enum IndexProfile {
+    /// Only the indexes needed by the commands of this tool.
+    Minimal,
+    /// Reverse lookups along foreign keys.
+    #[default]
+    Standard,
+    /// Covering indexes for aggregations over the whole catalog.
+    Analytics,
+}
+
+impl IndexProfile {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Minimal => "minimal",
+            Self::Standard => "standard",
+            Self::Analytics => "analytics",
+        }
+    }
+}
+
 impl Database {
     fn new(conn: Connection) -> Self {
         Self { conn }
@@ -23,7 +72,8 @@ impl Database {
         let conn = Connection::open(path)?;
         conn.pragma_update(None, "foreign_keys", "ON")?;
         // Tuning for bulk-load: this database is a one-shot dump that can be
-        // regenerated if a crash leaves it inconsistent.
+        // regenerated if a crash leaves it inconsistent. `finalize` restores
+        // safe settings for readers.
         conn.pragma_update(None, "journal_mode", "OFF")?;
         conn.pragma_update(None, "synchronous", "OFF")?;
         conn.pragma_update(None, "temp_store", "MEMORY")?;
@@ -31,29 +81,174 @@ impl Database {
         Ok(Self::new(conn))
     }
 
+    /// Opens an existing database for incremental updates.
+    ///
+    /// Unlike `open`, the journal of the database is kept, so that a crash
+    /// cannot corrupt data that is not regenerated by `dump`.
+    pub fn open_for_update<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if !path.exists() {
+            anyhow::bail!("database does not exist at {}", path.display());
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        conn.execute_batch(include_str!("database/schema.sql"))?;
+        Ok(Self::new(conn))
+    }
+
+    /// Opens an existing database for querying.
+    pub fn open_read_only<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if !path.exists() {
+            anyhow::bail!("database does not exist at {}", path.display());
+        }
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        Ok(Self::new(conn))
+    }
+
+    /// Inserts rows in a single transaction, with multi-row statements.
     pub fn insert_many<R, I, E>(&mut self, rows: I) -> anyhow::Result<()>
     where
         R: Insertable,
         I: IntoIterator<Item = Result<R, E>>,
         E: Into<anyhow::Error>,
     {
-        R::insert_many(&mut self.conn, rows)
+        self.insert_many_with_mode(rows, InsertMode::default())
+    }
+
+    pub fn insert_many_with_mode<R, I, E>(
+        &mut self,
+        rows: I,
+        mode: InsertMode,
+    ) -> anyhow::Result<()>
+    where
+        R: Insertable,
+        I: IntoIterator<Item = Result<R, E>>,
+        E: Into<anyhow::Error>,
+    {
+        R::insert_many(&mut self.conn, rows, mode)
+    }
+
+    /// Creates the indexes of the given profile, and of the smaller ones.
+    pub fn create_indexes(&self, profile: IndexProfile) -> anyhow::Result<()> {
+        const PROFILES: [(IndexProfile, &str); 3] = [
+            (
+                IndexProfile::Minimal,
+                include_str!("database/indexes/minimal.sql"),
+            ),
+            (
+                IndexProfile::Standard,
+                include_str!("database/indexes/standard.sql"),
+            ),
+            (
+                IndexProfile::Analytics,
+                include_str!("database/indexes/analytics.sql"),
+            ),
+        ];
+        for (_, sql) in PROFILES.iter().filter(|(p, _)| *p <= profile) {
+            self.conn.execute_batch(sql)?;
+        }
+        Ok(())
     }
 
-    pub fn create_indexes(&self) -> anyhow::Result<()> {
+    /// Gathers statistics about the tables and indexes for the query planner.
+    pub fn optimize(&self) -> anyhow::Result<()> {
+        self.conn.execute_batch("ANALYZE; PRAGMA optimize;")?;
+        Ok(())
+    }
+
+    /// Creates views for common joins.
+    pub fn create_views(&self) -> anyhow::Result<()> {
         self.conn
-            .execute_batch(include_str!("database/indexes.sql"))?;
+            .execute_batch(include_str!("database/views.sql"))?;
         Ok(())
     }
 }
 
+/// Returns the tables referenced by foreign keys of each table of the schema,
+/// excluding self-references.
+pub fn table_dependencies() -> anyhow::Result<BTreeMap<String, BTreeSet<String>>> {
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(include_str!("database/schema.sql"))?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT t.name, fk."table"
+        FROM sqlite_schema AS t
+        LEFT JOIN pragma_foreign_key_list(t.name) AS fk ON fk."table" != t.name
+        WHERE t.type = 'table'
+        "#,
+    )?;
+    let mut dependencies: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+        let (table, referenced): (String, Option<String>) = row?;
+        let entry = dependencies.entry(table).or_default();
+        entry.extend(referenced);
+    }
+    Ok(dependencies)
+}
+
+/// Returns the parameters selecting inventories of a given version, bound to
+/// `(NOT ?latest_only OR i.is_latest) AND (?version IS NULL OR i.version = ?version)`.
+pub(crate) fn version_params(version: InventoryVersion) -> (bool, Option<u32>) {
+    match version {
+        InventoryVersion::Latest => (true, None),
+        InventoryVersion::All => (false, None),
+        InventoryVersion::Exact(version) => (false, Some(version)),
+    }
+}
+
+/// How rows are bound to insert statements.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum InsertMode {
+    /// One statement per row.
+    RowByRow,
+    /// Statements inserting as many rows as SQLite can bind at once.
+    #[default]
+    Batched,
+}
+
+impl InsertMode {
+    fn batch_size<R>(self, conn: &Connection) -> anyhow::Result<usize>
+    where
+        R: InsertableSealed,
+    {
+        Ok(match self {
+            Self::RowByRow => 1,
+            Self::Batched => {
+                let max_variables = conn.limit(Limit::SQLITE_LIMIT_VARIABLE_NUMBER)?;
+                (usize::try_from(max_variables)? / R::COLUMNS.len()).clamp(1, MAX_BATCH_SIZE)
+            }
+        })
+    }
+}
+
+/// The maximum number of rows inserted by a single statement. Beyond that,
+/// larger statements do not make inserts any faster.
+const MAX_BATCH_SIZE: usize = 512;
+
 #[allow(private_bounds)]
 pub trait Insertable: InsertableSealed {}
 
 trait InsertableSealed: Sized {
-    const INSERT_STMT: &str;
+    const TABLE: &str;
+    /// The columns of the table, in the order they are bound by `bind_row`.
+    const COLUMNS: &[&str];
+    /// A condition on the inserted values, named `column1`, `column2`, etc.
+    /// If set, rows that do not satisfy it are skipped, as well as rows that
+    /// conflict with existing ones.
+    const FILTER: Option<&str> = None;
 
-    fn insert_row(stmt: &mut CachedStatement, row: &Self) -> anyhow::Result<()>;
+    /// Binds the values of a row, starting from the parameter after `offset`.
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()>;
 
     fn pre_hook(_tx: &Transaction) -> anyhow::Result<()> {
         Ok(())
@@ -63,295 +258,359 @@ trait InsertableSealed: Sized {
         Ok(())
     }
 
-    fn insert_many<I, E>(conn: &mut Connection, rows: I) -> anyhow::Result<()>
+    /// Returns a statement inserting `rows` rows at once.
+    fn insert_stmt(rows: usize) -> String {
+        let row = format!("({})", vec!["?"; Self::COLUMNS.len()].join(", "));
+        let values = vec![row; rows].join(", ");
+        let columns = Self::COLUMNS.join(", ");
+        match Self::FILTER {
+            None => format!("INSERT INTO {} ({columns}) VALUES {values}", Self::TABLE),
+            Some(filter) => format!(
+                "INSERT OR IGNORE INTO {} ({columns}) SELECT * FROM (VALUES {values}) WHERE {filter}",
+                Self::TABLE
+            ),
+        }
+    }
+
+    fn insert_batch(stmt: &mut Statement, rows: &[Self]) -> anyhow::Result<()> {
+        for (i, row) in rows.iter().enumerate() {
+            Self::bind_row(stmt, i * Self::COLUMNS.len(), row)?;
+        }
+        stmt.raw_execute()?;
+        Ok(())
+    }
+
+    fn insert_many<I, E>(conn: &mut Connection, rows: I, mode: InsertMode) -> anyhow::Result<()>
     where
         I: IntoIterator<Item = Result<Self, E>>,
         E: Into<anyhow::Error>,
     {
         let tx = conn.transaction()?;
         Self::pre_hook(&tx)?;
-        let mut stmt = tx.prepare_cached(Self::INSERT_STMT)?;
+        let batch_size = mode.batch_size::<Self>(&tx)?;
+        let mut stmt = tx.prepare(&Self::insert_stmt(batch_size))?;
+        let mut batch = Vec::with_capacity(batch_size);
         for row in rows {
-            let row = row.map_err(Into::into)?;
-            Self::insert_row(&mut stmt, &row)?;
+            batch.push(row.map_err(Into::into)?);
+            if batch.len() == batch_size {
+                Self::insert_batch(&mut stmt, &batch)?;
+                batch.clear();
+            }
         }
         drop(stmt);
+        if !batch.is_empty() {
+            let mut stmt = tx.prepare(&Self::insert_stmt(batch.len()))?;
+            Self::insert_batch(&mut stmt, &batch)?;
+        }
         Self::post_hook(&tx)?;
         tx.commit()?;
         Ok(())
     }
 }
 
+fn bind_params(stmt: &mut Statement, offset: usize, params: &[&dyn ToSql]) -> rusqlite::Result<()> {
+    for (i, param) in params.iter().enumerate() {
+        stmt.raw_bind_parameter(offset + i + 1, param)?;
+    }
+    Ok(())
+}
+
 impl Insertable for record::Inventory {}
 
 impl InsertableSealed for record::Inventory {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO inventories (
-            id,
-            version,
-            set_num
-        )
-        VALUES (?, ?, ?)
-    "#;
+    const TABLE: &str = "inventories";
+    const COLUMNS: &[&str] = &["id", "version", "kind", "set_num", "fig_num"];
+
+    // Dangling inventories are found once all of them are loaded, rather than
+    // failing on the first one with an opaque constraint error.
+    fn pre_hook(tx: &Transaction) -> anyhow::Result<()> {
+        tx.pragma_update(None, "defer_foreign_keys", "ON")?;
+        Ok(())
+    }
 
-    fn insert_row(stmt: &mut CachedStatement, row: &record::Inventory) -> anyhow::Result<()> {
-        stmt.execute(params![row.id, row.version, row.set_num])?;
+    // Rebrickable has inventories of unknown sets and minifigures, which are
+    // moved to `skipped_inventories` for `check` to report them.
+    fn post_hook(tx: &Transaction) -> anyhow::Result<()> {
+        let skipped = tx.execute(
+            r#"
+            INSERT INTO skipped_inventories (id, version, kind, item_num)
+            SELECT i.id, i.version, i.kind, COALESCE(i.set_num, i.fig_num)
+            FROM pragma_foreign_key_check('inventories') AS fk
+            JOIN inventories AS i ON i.rowid = fk.rowid
+            "#,
+            [],
+        )?;
+        if skipped > 0 {
+            let examples: Vec<String> = tx
+                .prepare(
+                    r#"
+                    SELECT format('%d (%s)', id, item_num)
+                    FROM skipped_inventories
+                    ORDER BY id
+                    LIMIT 10
+                    "#,
+                )?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            tracing::warn!(
+                "skipped {skipped} inventories referencing unknown sets or minifigures: {}{}",
+                examples.join(", "),
+                if skipped > examples.len() {
+                    ", ..."
+                } else {
+                    ""
+                },
+            );
+            tx.execute(
+                "DELETE FROM inventories WHERE id IN (SELECT id FROM skipped_inventories)",
+                [],
+            )?;
+        }
+
+        // A single pass: no index exists yet, so a correlated subquery per
+        // row would be quadratic.
+        tx.execute_batch(
+            r#"
+            UPDATE inventories AS i
+            SET is_latest = i.version = latest.version
+            FROM (
+                SELECT id, MAX(version) OVER (PARTITION BY set_num, fig_num) AS version
+                FROM inventories
+            ) AS latest
+            WHERE latest.id = i.id
+            "#,
+        )?;
         Ok(())
     }
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        let kind = row.kind();
+        bind_params(
+            stmt,
+            offset,
+            params![
+                row.id,
+                row.version,
+                kind.as_str(),
+                (kind == InventoryKind::Set).then_some(&row.set_num),
+                (kind == InventoryKind::Minifig).then_some(&row.set_num),
+            ],
+        )
+    }
 }
 
+/// Skips the contents of the inventories skipped when loading them.
+const SKIPPED_INVENTORY_FILTER: &str = "column1 NOT IN (SELECT id FROM skipped_inventories)";
+
 impl Insertable for record::InventoryPart {}
 
 impl InsertableSealed for record::InventoryPart {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO inventory_parts (
-            inventory_id,
-            part_num,
-            color_id,
-            quantity,
-            is_spare,
-            img_url
+    const TABLE: &str = "inventory_parts";
+    const COLUMNS: &[&str] = &[
+        "inventory_id",
+        "part_num",
+        "color_id",
+        "quantity",
+        "is_spare",
+        "img_url",
+    ];
+    const FILTER: Option<&str> = Some(SKIPPED_INVENTORY_FILTER);
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![
+                row.inventory_id,
+                row.part_num,
+                row.color_id,
+                row.quantity,
+                row.is_spare,
+                row.img_url.as_ref().map(Url::as_str),
+            ],
         )
-        VALUES (?, ?, ?, ?, ?, ?)
-    "#;
-
-    fn insert_row(stmt: &mut CachedStatement, row: &record::InventoryPart) -> anyhow::Result<()> {
-        stmt.execute(params![
-            row.inventory_id,
-            row.part_num,
-            row.color_id,
-            row.quantity,
-            row.is_spare,
-            row.img_url.as_ref().map(Url::as_str),
-        ])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::InventoryMinifig {}
 
 impl InsertableSealed for record::InventoryMinifig {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO inventory_minifigs (
-            inventory_id,
-            fig_num,
-            quantity
+    const TABLE: &str = "inventory_minifigs";
+    const COLUMNS: &[&str] = &["inventory_id", "fig_num", "quantity"];
+    const FILTER: Option<&str> = Some(SKIPPED_INVENTORY_FILTER);
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![row.inventory_id, row.fig_num, row.quantity],
         )
-        VALUES (?, ?, ?)
-    "#;
-
-    fn insert_row(
-        stmt: &mut CachedStatement,
-        row: &record::InventoryMinifig,
-    ) -> anyhow::Result<()> {
-        stmt.execute(params![row.inventory_id, row.fig_num, row.quantity])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::InventorySet {}
 
 impl InsertableSealed for record::InventorySet {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO inventory_sets (
-            inventory_id,
-            set_num,
-            quantity
+    const TABLE: &str = "inventory_sets";
+    const COLUMNS: &[&str] = &["inventory_id", "set_num", "quantity"];
+    const FILTER: Option<&str> = Some(SKIPPED_INVENTORY_FILTER);
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![row.inventory_id, row.set_num, row.quantity],
         )
-        VALUES (?, ?, ?)
-    "#;
-
-    fn insert_row(stmt: &mut CachedStatement, row: &record::InventorySet) -> anyhow::Result<()> {
-        stmt.execute(params![row.inventory_id, row.set_num, row.quantity])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::Part {}
 
 impl InsertableSealed for record::Part {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO parts (
-            part_num,
-            name,
-            part_cat_id,
-            part_material
+    const TABLE: &str = "parts";
+    const COLUMNS: &[&str] = &["part_num", "name", "part_cat_id", "part_material"];
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![
+                row.part_num,
+                row.name,
+                row.part_cat_id,
+                row.part_material.as_str(),
+            ],
         )
-        VALUES (?, ?, ?, ?)
-    "#;
-
-    fn insert_row(stmt: &mut CachedStatement, row: &record::Part) -> anyhow::Result<()> {
-        stmt.execute(params![
-            row.part_num,
-            row.name,
-            row.part_cat_id,
-            row.part_material.as_str(),
-        ])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::PartCategory {}
 
 impl InsertableSealed for record::PartCategory {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO part_categories (
-            id,
-            name
-        )
-        VALUES (?, ?)
-    "#;
+    const TABLE: &str = "part_categories";
+    const COLUMNS: &[&str] = &["id", "name"];
 
-    fn insert_row(stmt: &mut CachedStatement, row: &record::PartCategory) -> anyhow::Result<()> {
-        stmt.execute(params![row.id, row.name])?;
-        Ok(())
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(stmt, offset, params![row.id, row.name])
     }
 }
 
 impl Insertable for record::PartRelationship {}
 
 impl InsertableSealed for record::PartRelationship {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO part_relationships (
-            rel_type,
-            child_part_num,
-            parent_part_num
+    const TABLE: &str = "part_relationships";
+    const COLUMNS: &[&str] = &["rel_type", "child_part_num", "parent_part_num"];
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![
+                row.rel_type.as_str(),
+                row.child_part_num,
+                row.parent_part_num,
+            ],
         )
-        VALUES (?, ?, ?)
-    "#;
-
-    fn insert_row(
-        stmt: &mut CachedStatement,
-        row: &record::PartRelationship,
-    ) -> anyhow::Result<()> {
-        stmt.execute(params![
-            row.rel_type.as_str(),
-            row.child_part_num,
-            row.parent_part_num,
-        ])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::Element {}
 
 impl InsertableSealed for record::Element {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO elements (
-            element_id,
-            part_num,
-            color_id,
-            design_id
+    const TABLE: &str = "elements";
+    const COLUMNS: &[&str] = &["element_id", "part_num", "color_id", "design_id"];
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![row.element_id, row.part_num, row.color_id, row.design_id],
         )
-        VALUES (?, ?, ?, ?)
-    "#;
-
-    fn insert_row(stmt: &mut CachedStatement, row: &record::Element) -> anyhow::Result<()> {
-        stmt.execute(params![
-            row.element_id,
-            row.part_num,
-            row.color_id,
-            row.design_id,
-        ])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::Color {}
 
 impl InsertableSealed for record::Color {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO colors (
-            id,
-            name,
-            rgb,
-            is_trans,
-            num_parts,
-            num_sets,
-            first_year,
-            last_year
+    const TABLE: &str = "colors";
+    const COLUMNS: &[&str] = &[
+        "id",
+        "name",
+        "rgb",
+        "is_trans",
+        "num_parts",
+        "num_sets",
+        "first_year",
+        "last_year",
+    ];
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![
+                row.id,
+                row.name,
+                encode_rgb(row.rgb),
+                row.is_trans,
+                row.num_parts,
+                row.num_sets,
+                row.first_year,
+                row.last_year,
+            ],
         )
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
-    "#;
-
-    fn insert_row(stmt: &mut CachedStatement, row: &record::Color) -> anyhow::Result<()> {
-        stmt.execute(params![
-            row.id,
-            row.name,
-            encode_rgb(row.rgb),
-            row.is_trans,
-            row.num_parts,
-            row.num_sets,
-            row.first_year,
-            row.last_year,
-        ])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::Minifig {}
 
 impl InsertableSealed for record::Minifig {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO minifigs (
-            fig_num,
-            name,
-            num_parts,
-            img_url
+    const TABLE: &str = "minifigs";
+    const COLUMNS: &[&str] = &["fig_num", "name", "num_parts", "img_url"];
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![row.fig_num, row.name, row.num_parts, row.img_url.as_str()],
         )
-        VALUES (?, ?, ?, ?)
-    "#;
-
-    fn insert_row(stmt: &mut CachedStatement, row: &record::Minifig) -> anyhow::Result<()> {
-        stmt.execute(params![
-            row.fig_num,
-            row.name,
-            row.num_parts,
-            row.img_url.as_str(),
-        ])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::Set {}
 
 impl InsertableSealed for record::Set {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO sets (
-            set_num,
-            name,
-            year,
-            theme_id,
-            num_parts,
-            img_url
+    const TABLE: &str = "sets";
+    const COLUMNS: &[&str] = &[
+        "set_num",
+        "name",
+        "year",
+        "theme_id",
+        "num_parts",
+        "img_url",
+    ];
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![
+                row.set_num,
+                row.name,
+                row.year,
+                row.theme_id,
+                row.num_parts,
+                row.img_url.as_str(),
+            ],
         )
-        VALUES (?, ?, ?, ?, ?, ?)
-    "#;
-
-    fn insert_row(stmt: &mut CachedStatement, row: &record::Set) -> anyhow::Result<()> {
-        stmt.execute(params![
-            row.set_num,
-            row.name,
-            row.year,
-            row.theme_id,
-            row.num_parts,
-            row.img_url.as_str(),
-        ])?;
-        Ok(())
     }
 }
 
 impl Insertable for record::Theme {}
 
 impl InsertableSealed for record::Theme {
-    const INSERT_STMT: &str = r#"
-        INSERT INTO themes (
-            id,
-            name,
-            parent_id
-        )
-        VALUES (?, ?, ?)
-    "#;
+    const TABLE: &str = "themes";
+    const COLUMNS: &[&str] = &["id", "name", "parent_id"];
 
     // Rows are not inserted in topological order, so some records reference a
     // `parent_id` that does not yet exist. Defer foreign-key checks to commit
@@ -361,10 +620,53 @@ impl InsertableSealed for record::Theme {
         Ok(())
     }
 
-    fn insert_row(stmt: &mut CachedStatement, row: &record::Theme) -> anyhow::Result<()> {
-        stmt.execute(params![row.id, row.name, row.parent_id])?;
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(stmt, offset, params![row.id, row.name, row.parent_id])
+    }
+}
+
+impl Insertable for api::ColorExternalId {}
+
+impl InsertableSealed for api::ColorExternalId {
+    const TABLE: &str = "color_external_ids";
+    const COLUMNS: &[&str] = &["color_id", "source", "external_id", "name"];
+    // The API may know about colours that are not in the dumps yet.
+    const FILTER: Option<&str> = Some("column1 IN (SELECT id FROM colors)");
+
+    fn pre_hook(tx: &Transaction) -> anyhow::Result<()> {
+        tx.execute("DELETE FROM color_external_ids", [])?;
+        Ok(())
+    }
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![row.color_id, row.source, row.external_id, row.name],
+        )
+    }
+}
+
+impl Insertable for api::PartExternalId {}
+
+impl InsertableSealed for api::PartExternalId {
+    const TABLE: &str = "part_external_ids";
+    const COLUMNS: &[&str] = &["part_num", "source", "external_id"];
+    // The API may know about parts that are not in the dumps yet.
+    const FILTER: Option<&str> = Some("column1 IN (SELECT part_num FROM parts)");
+
+    fn pre_hook(tx: &Transaction) -> anyhow::Result<()> {
+        tx.execute("DELETE FROM part_external_ids", [])?;
         Ok(())
     }
+
+    fn bind_row(stmt: &mut Statement, offset: usize, row: &Self) -> rusqlite::Result<()> {
+        bind_params(
+            stmt,
+            offset,
+            params![row.part_num, row.source, row.external_id],
+        )
+    }
 }
 
 fn encode_rgb(rgb: Rgb) -> String {
@@ -372,10 +674,34 @@ fn encode_rgb(rgb: Rgb) -> String {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use std::{collections::BTreeSet, convert::Infallible, path::Path};
+
+    use rusqlite::Connection;
     use tempfile::tempdir;
 
-    use super::Database;
+    use super::{Database, IndexProfile, InsertMode, MAX_BATCH_SIZE};
+    use crate::{
+        rebrickable::{api, record},
+        types::PartMaterial,
+    };
+
+    /// Opens an in-memory database populated with a small fixture dataset.
+    pub(crate) fn open_fixture() -> anyhow::Result<Database> {
+        let conn = Connection::open_in_memory()?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        conn.execute_batch(include_str!("database/schema.sql"))?;
+        conn.execute_batch(include_str!("database/fixture.sql"))?;
+        Ok(Database::new(conn))
+    }
+
+    /// Writes the fixture dataset to a database file.
+    pub(crate) fn write_fixture(path: &Path) -> anyhow::Result<()> {
+        let db = Database::open(path)?;
+        db.conn
+            .execute_batch(include_str!("database/fixture.sql"))?;
+        Ok(())
+    }
 
     #[test]
     fn init() -> anyhow::Result<()> {
@@ -384,4 +710,241 @@ mod tests {
         Database::open(path)?;
         Ok(())
     }
+
+    #[test]
+    fn open_for_update() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("test.db");
+        assert!(Database::open_for_update(&path).is_err());
+
+        Database::open(&path)?;
+        let db = Database::open_for_update(&path)?;
+        let journal_mode: String = db
+            .conn
+            .pragma_query_value(None, "journal_mode", |row| row.get(0))?;
+        assert_eq!(journal_mode, "delete");
+        let synchronous: i64 = db
+            .conn
+            .pragma_query_value(None, "synchronous", |row| row.get(0))?;
+        assert_ne!(synchronous, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn table_dependencies() -> anyhow::Result<()> {
+        let dependencies = super::table_dependencies()?;
+        assert_eq!(
+            dependencies["elements"],
+            BTreeSet::from(["colors".to_owned(), "parts".to_owned()])
+        );
+        // Self-references are ignored.
+        assert!(dependencies["themes"].is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn inventory_kinds() -> anyhow::Result<()> {
+        let mut db = open_fixture()?;
+        let inventory = |id, set_num: &str| {
+            Ok::<_, Infallible>(record::Inventory {
+                id,
+                version: 1,
+                set_num: set_num.to_owned(),
+            })
+        };
+        db.insert_many([inventory(10, "1001-1"), inventory(11, "fig-000001")])?;
+        let kinds: Vec<(String, Option<String>, Option<String>)> = db
+            .conn
+            .prepare("SELECT kind, set_num, fig_num FROM inventories WHERE id >= 10 ORDER BY id")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            kinds,
+            [
+                ("set".to_owned(), Some("1001-1".to_owned()), None),
+                ("minifig".to_owned(), None, Some("fig-000001".to_owned())),
+            ]
+        );
+
+        // Dangling inventories are skipped, along with their contents.
+        db.insert_many([inventory(12, "9999-1"), inventory(13, "fig-999999")])?;
+        db.insert_many([Ok::<_, Infallible>(record::InventoryPart {
+            inventory_id: 12,
+            part_num: "3001".to_owned(),
+            color_id: 4,
+            quantity: 1,
+            is_spare: false,
+            img_url: None,
+        })])?;
+        let count = |sql| db.conn.query_row(sql, [], |row| row.get::<_, i64>(0));
+        assert_eq!(count("SELECT COUNT(*) FROM inventories WHERE id >= 12")?, 0);
+        assert_eq!(count("SELECT COUNT(*) FROM skipped_inventories")?, 2);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM inventory_parts WHERE inventory_id = 12")?,
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn latest_inventories() -> anyhow::Result<()> {
+        let mut db = open_fixture()?;
+        db.insert_many([
+            Ok::<_, Infallible>(record::Inventory {
+                id: 10,
+                version: 2,
+                set_num: "1001-1".to_owned(),
+            }),
+            Ok(record::Inventory {
+                id: 11,
+                version: 1,
+                set_num: "fig-000001".to_owned(),
+            }),
+        ])?;
+        let latest: Vec<i64> = db
+            .conn
+            .prepare("SELECT id FROM inventories WHERE is_latest ORDER BY id")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        // Minifigures have a single version, so both of their inventories
+        // are the latest.
+        assert_eq!(latest, [2, 4, 10, 11]);
+        Ok(())
+    }
+
+    #[test]
+    fn index_profiles() -> anyhow::Result<()> {
+        let mut counts = Vec::new();
+        for profile in [
+            IndexProfile::Minimal,
+            IndexProfile::Standard,
+            IndexProfile::Analytics,
+        ] {
+            let db = open_fixture()?;
+            db.create_indexes(profile)?;
+            db.optimize()?;
+            let count: i64 = db.conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_schema WHERE type = 'index' AND sql IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )?;
+            counts.push(count);
+        }
+        assert_eq!(counts, [3, 12, 18]);
+        Ok(())
+    }
+
+    #[test]
+    fn views() -> anyhow::Result<()> {
+        let db = open_fixture()?;
+        db.create_views()?;
+        let query = |sql: &str| -> rusqlite::Result<Vec<String>> {
+            db.conn
+                .prepare(sql)?
+                .query_map([], |row| row.get(0))?
+                .collect()
+        };
+
+        assert_eq!(
+            query(
+                "SELECT format('%d %s', id, COALESCE(set_num, fig_num)) FROM latest_inventories ORDER BY id"
+            )?,
+            ["2 1000-1", "3 1001-1", "4 fig-000001"]
+        );
+        assert_eq!(
+            query(
+                "SELECT format('%s %s %s %s %s x%d%s', set_num, part_num, part_name, category, color_name, quantity, iif(is_spare, ' (spare)', '')) \
+                 FROM set_parts_flat ORDER BY set_num, is_spare"
+            )?,
+            [
+                "1000-1 3001 Brick 2 x 4 Bricks Red x4",
+                "1000-1 3001 Brick 2 x 4 Bricks Red x1 (spare)",
+                "1001-1 3003 Brick 2 x 2 Bricks White x3",
+            ]
+        );
+        assert_eq!(
+            query(
+                "SELECT format('%s %s %s', fig_num, part_num, color_name) FROM minifig_parts ORDER BY part_num"
+            )?,
+            ["fig-000001 3626c Yellow", "fig-000001 973pr0001 Blue"]
+        );
+        assert_eq!(
+            query("SELECT format('%s %s x%d', set_num, fig_name, quantity) FROM set_minifigs")?,
+            ["1000-1 Firefighter x1"]
+        );
+        assert_eq!(
+            query(
+                "SELECT format('%s %s %d %d', part_num, color_name, num_sets, num_minifigs) \
+                 FROM part_colors ORDER BY part_num"
+            )?,
+            [
+                "3001 Red 1 0",
+                "3003 White 1 0",
+                "3626c Yellow 0 1",
+                "973pr0001 Blue 0 1"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn external_ids_of_unknown_parts() -> anyhow::Result<()> {
+        let mut db = open_fixture()?;
+        let external_id = |part_num: &str| {
+            Ok::<_, Infallible>(api::PartExternalId {
+                part_num: part_num.to_owned(),
+                source: "BrickLink".to_owned(),
+                external_id: part_num.to_owned(),
+            })
+        };
+        db.insert_many([external_id("3001"), external_id("unknown")])?;
+        let count: u32 =
+            db.conn
+                .query_row("SELECT COUNT(*) FROM part_external_ids", [], |row| {
+                    row.get(0)
+                })?;
+        assert_eq!(count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn insert_modes() -> anyhow::Result<()> {
+        // Enough rows for a full batch and a partial one.
+        let parts: Vec<_> = (0..MAX_BATCH_SIZE + 3)
+            .map(|i| {
+                Ok::<_, Infallible>(record::Part {
+                    part_num: format!("bench{i}"),
+                    name: format!("Part {i}"),
+                    part_cat_id: 11,
+                    part_material: PartMaterial::Plastic,
+                })
+            })
+            .collect();
+        let expected: Vec<_> = (0..MAX_BATCH_SIZE + 3)
+            .map(|i| {
+                (
+                    format!("bench{i}"),
+                    format!("Part {i}"),
+                    11,
+                    "plastic".to_owned(),
+                )
+            })
+            .collect();
+        for mode in [InsertMode::RowByRow, InsertMode::Batched] {
+            let mut db = open_fixture()?;
+            db.insert_many_with_mode(parts.clone(), mode)?;
+            let rows: Vec<(String, String, i32, String)> = db
+                .conn
+                .prepare(
+                    "SELECT part_num, name, part_cat_id, part_material FROM parts \
+                     WHERE part_num LIKE 'bench%' ORDER BY rowid",
+                )?
+                .query_map([], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })?
+                .collect::<Result<_, _>>()?;
+            assert_eq!(rows, expected, "{mode:?}");
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use super::Database;
+
+/// A set that is not in the previous database.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct NewSet {
+    pub set_num: String,
+    pub name: String,
+    pub year: i32,
+    pub num_parts: u32,
+    /// The names of the theme of the set and its ancestors, from the root.
+    pub theme_path: Vec<String>,
+    pub img_url: String,
+}
+
+/// A minifigure that is not in the previous database.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct NewMinifig {
+    pub fig_num: String,
+    pub name: String,
+    pub num_parts: u32,
+    pub img_url: String,
+}
+
+/// A theme that is not in the previous database.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct NewTheme {
+    pub id: i32,
+    pub name: String,
+    /// The names of the theme and its ancestors, from the root.
+    pub path: Vec<String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Serialize)]
+pub struct Additions {
+    pub sets: Vec<NewSet>,
+    pub minifigs: Vec<NewMinifig>,
+    pub themes: Vec<NewTheme>,
+}
+
+// Theme paths are joined with a separator that cannot appear in names, and
+// split back in Rust.
+const THEME_PATHS: &str = r#"
+    WITH RECURSIVE theme_paths (id, path) AS (
+        SELECT id, name
+        FROM main.themes
+        WHERE parent_id IS NULL
+        UNION ALL
+        SELECT t.id, p.path || char(31) || t.name
+        FROM main.themes AS t
+        JOIN theme_paths AS p ON p.id = t.parent_id
+    )
+"#;
+
+impl Database {
+    /// Lists the sets, minifigures and themes of this database that are not
+    /// in a previous one, ordered by identifier.
+    pub fn additions(&self, previous: &Path) -> anyhow::Result<Additions> {
+        let Some(previous) = previous.to_str() else {
+            anyhow::bail!("invalid database path {}", previous.display());
+        };
+        self.conn
+            .execute("ATTACH DATABASE ?1 AS previous", [previous])?;
+        let additions = query_additions(&self.conn);
+        self.conn.execute_batch("DETACH DATABASE previous")?;
+        additions
+    }
+}
+
+fn query_additions(conn: &Connection) -> anyhow::Result<Additions> {
+    let split = |path: String| path.split('\x1f').map(str::to_owned).collect();
+    let sets = conn
+        .prepare(&format!(
+            r#"
+            {THEME_PATHS}
+            SELECT s.set_num, s.name, s.year, s.num_parts, p.path, s.img_url
+            FROM main.sets AS s
+            JOIN theme_paths AS p ON p.id = s.theme_id
+            WHERE s.set_num NOT IN (SELECT set_num FROM previous.sets)
+            ORDER BY s.set_num
+            "#
+        ))?
+        .query_map([], |row| {
+            Ok(NewSet {
+                set_num: row.get(0)?,
+                name: row.get(1)?,
+                year: row.get(2)?,
+                num_parts: row.get(3)?,
+                theme_path: split(row.get(4)?),
+                img_url: row.get(5)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    let minifigs = conn
+        .prepare(
+            r#"
+            SELECT fig_num, name, num_parts, img_url
+            FROM main.minifigs
+            WHERE fig_num NOT IN (SELECT fig_num FROM previous.minifigs)
+            ORDER BY fig_num
+            "#,
+        )?
+        .query_map([], |row| {
+            Ok(NewMinifig {
+                fig_num: row.get(0)?,
+                name: row.get(1)?,
+                num_parts: row.get(2)?,
+                img_url: row.get(3)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    let themes = conn
+        .prepare(&format!(
+            r#"
+            {THEME_PATHS}
+            SELECT t.id, t.name, p.path
+            FROM main.themes AS t
+            JOIN theme_paths AS p ON p.id = t.id
+            WHERE t.id NOT IN (SELECT id FROM previous.themes)
+            ORDER BY t.id
+            "#
+        ))?
+        .query_map([], |row| {
+            Ok(NewTheme {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                path: split(row.get(2)?),
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(Additions {
+        sets,
+        minifigs,
+        themes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::database::{Database, tests::open_fixture};
+
+    #[test]
+    fn additions() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let previous = dir.path().join("previous.db");
+        Database::open(&previous)?.conn.execute_batch(
+            r#"
+            INSERT INTO themes (id, name, parent_id) VALUES (1, 'Town', NULL), (2, 'Fire', 1);
+            INSERT INTO sets (set_num, name, year, theme_id, num_parts, img_url)
+            VALUES ('1000-1', 'Fire Station', 2020, 2, 10, '');
+            "#,
+        )?;
+
+        let db = open_fixture()?;
+        let additions = db.additions(&previous)?;
+        let sets: Vec<_> = additions.sets.iter().map(|set| &set.set_num).collect();
+        assert_eq!(sets, ["1001-1"]);
+        assert_eq!(additions.sets[0].theme_path, ["Town", "Fire"]);
+        assert_eq!(
+            additions.sets[0].img_url,
+            "https://cdn.rebrickable.com/media/sets/1001-1.jpg"
+        );
+        let minifigs: Vec<_> = additions.minifigs.iter().map(|fig| &fig.fig_num).collect();
+        assert_eq!(minifigs, ["fig-000001"]);
+        let themes: Vec<_> = additions.themes.iter().map(|theme| &theme.name).collect();
+        assert_eq!(themes, ["Space"]);
+
+        // The previous database is detached, so it can be attached again.
+        assert_eq!(db.additions(&previous)?, additions);
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use rusqlite::OptionalExtension;
+
+use super::Database;
+
+/// The tables filled by other commands than `dump`, which a rebuilt database
+/// takes over from the one it replaces.
+pub const CARRIED_TABLES: &[&str] = &[
+    "color_external_ids",
+    "part_external_ids",
+    "user_setlists",
+    "user_setlist_sets",
+    "user_partlists",
+    "user_partlist_parts",
+    "user_lost_parts",
+    "images",
+];
+
+/// The outcome of carrying a table over.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct CarriedRows {
+    pub rows: usize,
+    /// The number of rows dropped because they reference rows that are no
+    /// longer in the database.
+    pub dropped: usize,
+}
+
+impl Database {
+    /// Copies the tables of `CARRIED_TABLES` from a previous database,
+    /// returning statistics by table.
+    ///
+    /// Tables missing from the previous database are skipped.
+    pub fn carry_over(
+        &mut self,
+        previous: &Path,
+    ) -> anyhow::Result<Vec<(&'static str, CarriedRows)>> {
+        let Some(previous) = previous.to_str() else {
+            anyhow::bail!("invalid database path {}", previous.display());
+        };
+        self.conn
+            .execute("ATTACH DATABASE ?1 AS previous", [previous])?;
+        let res = self.copy_carried_tables();
+        self.conn.execute("DETACH DATABASE previous", [])?;
+        res
+    }
+
+    fn copy_carried_tables(&mut self) -> anyhow::Result<Vec<(&'static str, CarriedRows)>> {
+        let tx = self.conn.transaction()?;
+        tx.pragma_update(None, "defer_foreign_keys", "ON")?;
+        let mut stats = Vec::new();
+        for &table in CARRIED_TABLES {
+            let exists = tx
+                .query_row(
+                    "SELECT 1 FROM previous.sqlite_schema WHERE type = 'table' AND name = ?1",
+                    [table],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !exists {
+                continue;
+            }
+            // Columns are listed explicitly, in case they were reordered.
+            let columns: Vec<String> = tx
+                .prepare("SELECT name FROM pragma_table_info(?1, 'main')")?
+                .query_map([table], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            let columns = columns.join(", ");
+            let rows = tx.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO main.{table} ({columns}) SELECT {columns} FROM previous.{table}"
+                ),
+                [],
+            )?;
+            let dropped = tx.execute(
+                &format!(
+                    "DELETE FROM main.{table} WHERE rowid IN (SELECT rowid FROM pragma_foreign_key_check('{table}', 'main'))"
+                ),
+                [],
+            )?;
+            stats.push((
+                table,
+                CarriedRows {
+                    rows: rows - dropped,
+                    dropped,
+                },
+            ));
+        }
+        tx.commit()?;
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::CarriedRows;
+    use crate::database::{CachedImage, Database, tests::open_fixture};
+
+    #[test]
+    fn carry_over() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let previous = dir.path().join("previous.db");
+        let image = CachedImage {
+            url: "https://cdn.rebrickable.com/media/sets/1000-1.jpg".to_owned(),
+            sha256: "00".repeat(32),
+            path: "/cache/00.jpg".to_owned(),
+            size: 42,
+            fetched_at: 1000,
+        };
+        let db = Database::open(&previous)?;
+        db.record_image(&image)?;
+        // The sets of the previous database are not needed.
+        db.conn.execute_batch(
+            r#"
+            PRAGMA foreign_keys = OFF;
+            INSERT INTO user_setlists VALUES (1, 'Mine', 1);
+            INSERT INTO user_setlist_sets VALUES (1, '1000-1', 1, 1), (1, '9999-1', 1, 1);
+            "#,
+        )?;
+        drop(db);
+
+        let mut db = open_fixture()?;
+        let carried = |stats: Vec<(&'static str, CarriedRows)>| {
+            stats
+                .into_iter()
+                .filter(|(_, carried)| *carried != CarriedRows::default())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            carried(db.carry_over(&previous)?),
+            [
+                (
+                    "user_setlists",
+                    CarriedRows {
+                        rows: 1,
+                        dropped: 0
+                    }
+                ),
+                (
+                    "user_setlist_sets",
+                    CarriedRows {
+                        rows: 1,
+                        dropped: 1
+                    }
+                ),
+                (
+                    "images",
+                    CarriedRows {
+                        rows: 1,
+                        dropped: 0
+                    }
+                ),
+            ]
+        );
+        assert_eq!(db.cached_images()?[&image.url], image);
+
+        // Rows already in the database are kept.
+        assert_eq!(
+            carried(db.carry_over(&previous)?),
+            [(
+                "user_setlist_sets",
+                CarriedRows {
+                    rows: 0,
+                    dropped: 1
+                }
+            )]
+        );
+        Ok(())
+    }
+}
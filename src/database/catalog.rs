@@ -0,0 +1,309 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use rusqlite::params;
+
+use super::Database;
+use crate::types::{InventoryVersion, Rgb};
+
+/// The whole catalogue, as needed to render it: themes, sets, minifigures,
+/// parts and colours, with the selected inventories of sets and the latest
+/// inventories of minifigures.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Catalog {
+    pub themes: Vec<CatalogTheme>,
+    pub sets: Vec<CatalogSet>,
+    pub minifigs: Vec<CatalogMinifig>,
+    pub parts: Vec<CatalogPart>,
+    pub colors: BTreeMap<i32, CatalogColor>,
+    /// The parts of the sets, by set number.
+    pub set_parts: BTreeMap<String, Vec<InventoryPart>>,
+    /// The minifigures of the sets, by set number.
+    pub set_minifigs: BTreeMap<String, Vec<InventoryMinifig>>,
+    /// The parts of the minifigures, by minifigure number.
+    pub minifig_parts: BTreeMap<String, Vec<InventoryPart>>,
+    /// The paths of the images fetched into the local cache, by URL.
+    pub cached_images: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CatalogTheme {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CatalogSet {
+    pub set_num: String,
+    pub name: String,
+    pub year: i32,
+    pub theme_id: i32,
+    pub num_parts: u32,
+    pub img_url: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CatalogMinifig {
+    pub fig_num: String,
+    pub name: String,
+    pub num_parts: u32,
+    pub img_url: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CatalogPart {
+    pub part_num: String,
+    pub name: String,
+    pub category: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CatalogColor {
+    pub name: String,
+    pub rgb: Rgb,
+    pub is_trans: bool,
+}
+
+/// A part line of an inventory.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct InventoryPart {
+    /// The version of the inventory.
+    pub version: u32,
+    pub part_num: String,
+    pub color_id: i32,
+    pub quantity: u32,
+    pub is_spare: bool,
+    pub img_url: Option<String>,
+}
+
+/// A minifigure line of an inventory.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct InventoryMinifig {
+    /// The version of the inventory.
+    pub version: u32,
+    pub fig_num: String,
+    pub quantity: u32,
+}
+
+impl Database {
+    /// Loads the catalogue, everything ordered by identifier, with the given
+    /// versions of the inventories of the sets.
+    pub fn catalog(&self, version: InventoryVersion) -> anyhow::Result<Catalog> {
+        let (latest_only, exact_version) = super::version_params(version);
+        let themes = self
+            .conn
+            .prepare("SELECT id, name, parent_id FROM themes ORDER BY id")?
+            .query_map([], |row| {
+                Ok(CatalogTheme {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    parent_id: row.get(2)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        let sets = self
+            .conn
+            .prepare(
+                r#"
+                SELECT set_num, name, year, theme_id, num_parts, img_url
+                FROM sets
+                ORDER BY set_num
+                "#,
+            )?
+            .query_map([], |row| {
+                Ok(CatalogSet {
+                    set_num: row.get(0)?,
+                    name: row.get(1)?,
+                    year: row.get(2)?,
+                    theme_id: row.get(3)?,
+                    num_parts: row.get(4)?,
+                    img_url: row.get(5)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        let minifigs = self
+            .conn
+            .prepare("SELECT fig_num, name, num_parts, img_url FROM minifigs ORDER BY fig_num")?
+            .query_map([], |row| {
+                Ok(CatalogMinifig {
+                    fig_num: row.get(0)?,
+                    name: row.get(1)?,
+                    num_parts: row.get(2)?,
+                    img_url: row.get(3)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        let parts = self
+            .conn
+            .prepare(
+                r#"
+                SELECT p.part_num, p.name, pc.name
+                FROM parts AS p
+                JOIN part_categories AS pc ON pc.id = p.part_cat_id
+                ORDER BY p.part_num
+                "#,
+            )?
+            .query_map([], |row| {
+                Ok(CatalogPart {
+                    part_num: row.get(0)?,
+                    name: row.get(1)?,
+                    category: row.get(2)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut colors = BTreeMap::new();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, rgb, is_trans FROM colors")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let rgb: String = row.get(2)?;
+            colors.insert(
+                row.get(0)?,
+                CatalogColor {
+                    name: row.get(1)?,
+                    rgb: Rgb::from_str(&rgb)?,
+                    is_trans: row.get(3)?,
+                },
+            );
+        }
+
+        let mut set_parts: BTreeMap<String, Vec<InventoryPart>> = BTreeMap::new();
+        let mut minifig_parts: BTreeMap<String, Vec<InventoryPart>> = BTreeMap::new();
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT i.set_num, i.fig_num, i.version, ip.part_num, ip.color_id, ip.quantity,
+                ip.is_spare, ip.img_url
+            FROM inventories AS i
+            JOIN inventory_parts AS ip ON ip.inventory_id = i.id
+            WHERE CASE
+                WHEN i.set_num IS NULL THEN i.is_latest
+                ELSE (NOT ?1 OR i.is_latest) AND (?2 IS NULL OR i.version = ?2)
+            END
+            ORDER BY i.version, ip.is_spare, ip.part_num, ip.color_id
+            "#,
+        )?;
+        let mut rows = stmt.query(params![latest_only, exact_version])?;
+        while let Some(row) = rows.next()? {
+            let part = InventoryPart {
+                version: row.get(2)?,
+                part_num: row.get(3)?,
+                color_id: row.get(4)?,
+                quantity: row.get(5)?,
+                is_spare: row.get(6)?,
+                img_url: row.get(7)?,
+            };
+            match (row.get(0)?, row.get(1)?) {
+                (Some(set_num), _) => set_parts.entry(set_num).or_default().push(part),
+                (None, Some(fig_num)) => minifig_parts.entry(fig_num).or_default().push(part),
+                (None, None) => {}
+            }
+        }
+
+        let mut set_minifigs: BTreeMap<String, Vec<InventoryMinifig>> = BTreeMap::new();
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT i.set_num, i.version, im.fig_num, im.quantity
+            FROM inventories AS i
+            JOIN inventory_minifigs AS im ON im.inventory_id = i.id
+            WHERE i.set_num IS NOT NULL
+                AND (NOT ?1 OR i.is_latest) AND (?2 IS NULL OR i.version = ?2)
+            ORDER BY i.version, im.fig_num
+            "#,
+        )?;
+        let mut rows = stmt.query(params![latest_only, exact_version])?;
+        while let Some(row) = rows.next()? {
+            set_minifigs
+                .entry(row.get(0)?)
+                .or_default()
+                .push(InventoryMinifig {
+                    version: row.get(1)?,
+                    fig_num: row.get(2)?,
+                    quantity: row.get(3)?,
+                });
+        }
+
+        // Databases created before the image cache have no `images` table.
+        let has_images: bool = self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = 'images')",
+            [],
+            |row| row.get(0),
+        )?;
+        let cached_images = if has_images {
+            self.cached_images()?
+                .into_values()
+                .map(|image| (image.url, image.path))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Catalog {
+            themes,
+            sets,
+            minifigs,
+            parts,
+            colors,
+            set_parts,
+            set_minifigs,
+            minifig_parts,
+            cached_images,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InventoryMinifig;
+    use crate::{
+        database::tests::open_fixture,
+        types::{InventoryVersion, Rgb},
+    };
+
+    #[test]
+    fn catalog() -> anyhow::Result<()> {
+        let db = open_fixture()?;
+        let catalog = db.catalog(InventoryVersion::Latest)?;
+        assert_eq!(catalog.themes.len(), 3);
+        assert_eq!(catalog.sets.len(), 2);
+        assert_eq!(catalog.parts.len(), 11);
+        assert_eq!(
+            catalog.colors[&4].rgb,
+            Rgb {
+                r: 0xc9,
+                g: 0x1a,
+                b: 0x09
+            }
+        );
+
+        // Only the latest inventory of a set is loaded, spares last.
+        let parts: Vec<_> = catalog.set_parts["1000-1"]
+            .iter()
+            .map(|part| (part.part_num.as_str(), part.quantity, part.is_spare))
+            .collect();
+        assert_eq!(parts, [("3001", 4, false), ("3001", 1, true)]);
+        assert_eq!(
+            catalog.set_minifigs["1000-1"],
+            [InventoryMinifig {
+                version: 2,
+                fig_num: "fig-000001".to_owned(),
+                quantity: 1
+            }]
+        );
+        assert_eq!(catalog.minifig_parts["fig-000001"].len(), 2);
+
+        // Other versions only apply to the inventories of sets.
+        let catalog = db.catalog(InventoryVersion::Exact(1))?;
+        let parts: Vec<_> = catalog.set_parts["1000-1"]
+            .iter()
+            .map(|part| (part.version, part.quantity))
+            .collect();
+        assert_eq!(parts, [(1, 5)]);
+        assert!(!catalog.set_minifigs.contains_key("1000-1"));
+        assert_eq!(catalog.minifig_parts["fig-000001"].len(), 2);
+        let catalog = db.catalog(InventoryVersion::All)?;
+        assert_eq!(catalog.set_parts["1000-1"].len(), 3);
+        Ok(())
+    }
+}
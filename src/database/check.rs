@@ -0,0 +1,226 @@
+use std::fmt;
+
+use super::Database;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, clap::ValueEnum)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A data-quality rule, checked by a query returning a description of each
+/// row that breaks it.
+#[derive(Copy, Clone, Debug)]
+pub struct Rule {
+    pub name: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    query: &'static str,
+    /// A table the query needs, the rule being skipped on databases created
+    /// before it existed.
+    requires: Option<&'static str>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "integrity",
+        severity: Severity::Error,
+        description: "SQLite integrity check",
+        query: r#"
+            SELECT integrity_check
+            FROM pragma_integrity_check
+            WHERE integrity_check != 'ok'
+        "#,
+        requires: None,
+    },
+    Rule {
+        name: "foreign-keys",
+        severity: Severity::Error,
+        description: "rows referencing missing rows",
+        query: r#"
+            SELECT format('%s row %d references a missing row of %s', "table", rowid, parent)
+            FROM pragma_foreign_key_check
+            ORDER BY "table", rowid
+        "#,
+        requires: None,
+    },
+    Rule {
+        name: "sets-without-inventory",
+        severity: Severity::Warning,
+        description: "sets without any inventory",
+        query: r#"
+            SELECT set_num
+            FROM sets
+            WHERE set_num NOT IN (
+                SELECT set_num FROM inventories WHERE set_num IS NOT NULL
+            )
+            ORDER BY set_num
+        "#,
+        requires: None,
+    },
+    Rule {
+        name: "skipped-inventories",
+        severity: Severity::Warning,
+        description: "inventories of unknown sets or minifigures, skipped by dump",
+        query: r#"
+            SELECT format('%d (%s)', id, item_num)
+            FROM skipped_inventories
+            ORDER BY id
+        "#,
+        requires: Some("skipped_inventories"),
+    },
+    // Rebrickable computes `num_parts` from the latest inventory, so older
+    // versions are not compared.
+    Rule {
+        name: "set-num-parts",
+        severity: Severity::Warning,
+        description: "sets whose number of parts disagrees with their latest inventory",
+        query: r#"
+            SELECT format(
+                '%s: num_parts is %d, inventory has %d',
+                s.set_num,
+                s.num_parts,
+                COALESCE(SUM(ip.quantity), 0)
+            )
+            FROM sets AS s
+            JOIN inventories AS i
+                ON i.set_num = s.set_num AND i.is_latest
+            LEFT JOIN inventory_parts AS ip
+                ON ip.inventory_id = i.id AND NOT ip.is_spare
+            GROUP BY s.set_num
+            HAVING s.num_parts != COALESCE(SUM(ip.quantity), 0)
+            ORDER BY s.set_num
+        "#,
+        requires: None,
+    },
+    Rule {
+        name: "colors-without-elements",
+        severity: Severity::Info,
+        description: "colours without any element",
+        query: r#"
+            SELECT format('%d (%s)', id, name)
+            FROM colors
+            WHERE id NOT IN (SELECT color_id FROM elements)
+            ORDER BY id
+        "#,
+        requires: None,
+    },
+    Rule {
+        name: "themes-without-sets",
+        severity: Severity::Info,
+        description: "themes without any set, including in their sub-themes",
+        query: r#"
+            WITH RECURSIVE used_themes (id) AS (
+                SELECT theme_id FROM sets
+                UNION
+                SELECT themes.parent_id
+                FROM themes
+                JOIN used_themes ON themes.id = used_themes.id
+                WHERE themes.parent_id IS NOT NULL
+            )
+            SELECT format('%d (%s)', id, name)
+            FROM themes
+            WHERE id NOT IN (SELECT id FROM used_themes)
+            ORDER BY id
+        "#,
+        requires: None,
+    },
+];
+
+/// The rows breaking a rule.
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub rule: &'static Rule,
+    pub count: usize,
+    /// The descriptions of the first rows breaking the rule.
+    pub examples: Vec<String>,
+}
+
+impl Database {
+    /// Checks every rule, returning the findings of the broken ones.
+    pub fn check(&self, max_examples: usize) -> anyhow::Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for rule in RULES {
+            if let Some(table) = rule.requires {
+                let exists: bool = self.conn.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = ?1)",
+                    [table],
+                    |row| row.get(0),
+                )?;
+                if !exists {
+                    continue;
+                }
+            }
+            // Each rule runs once, as some of them scan the whole database.
+            let mut stmt = self.conn.prepare(rule.query)?;
+            let mut rows = stmt.query([])?;
+            let mut count = 0;
+            let mut examples = Vec::new();
+            while let Some(row) = rows.next()? {
+                count += 1;
+                if examples.len() < max_examples {
+                    examples.push(row.get(0)?);
+                }
+            }
+            if count == 0 {
+                continue;
+            }
+            findings.push(Finding {
+                rule,
+                count,
+                examples,
+            });
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::tests::open_fixture;
+
+    #[test]
+    fn check() -> anyhow::Result<()> {
+        let db = open_fixture()?;
+        db.conn.execute(
+            "INSERT INTO skipped_inventories (id, version, kind, item_num) VALUES (9, 1, 'minifig', 'fig-999999')",
+            [],
+        )?;
+        let findings = db.check(1)?;
+        let summary: Vec<_> = findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.rule.name,
+                    finding.count,
+                    finding.examples.join("; "),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("skipped-inventories", 1, "9 (fig-999999)".to_owned()),
+                (
+                    "set-num-parts",
+                    1,
+                    "1000-1: num_parts is 10, inventory has 4".to_owned()
+                ),
+                ("colors-without-elements", 2, "0 (Black)".to_owned()),
+                ("themes-without-sets", 1, "3 (Space)".to_owned()),
+            ]
+        );
+        Ok(())
+    }
+}
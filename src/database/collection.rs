@@ -0,0 +1,209 @@
+use rusqlite::params;
+
+use super::Database;
+use crate::{rebrickable::list::SetListEntry, types::InventoryVersion};
+
+/// The loose parts of a collection, for a given part and colour.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CollectionPart {
+    pub part_num: String,
+    pub color_id: i32,
+    pub category: String,
+    pub quantity: u32,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct CollectionParts {
+    /// The parts, ordered by category, part and colour.
+    pub parts: Vec<CollectionPart>,
+    /// The owned sets that have no inventory of the selected version in the
+    /// database.
+    pub unknown_sets: Vec<String>,
+}
+
+// Sub-sets and minifigures are flattened recursively, each one contributing
+// its own inventory multiplied by the number of copies of its parent. The
+// owned sets contribute the selected versions of their inventory, while
+// sub-sets and minifigures only contribute their latest version.
+const COLLECTION_PARTS_QUERY: &str = r#"
+    WITH RECURSIVE
+    owned (set_num, quantity) AS (
+        SELECT set_num, SUM(quantity)
+        FROM temp.owned_sets
+        GROUP BY set_num
+    ),
+    flat (inventory_id, quantity) AS (
+        SELECT i.id, o.quantity
+        FROM owned AS o
+        JOIN inventories AS i ON i.set_num = o.set_num
+        WHERE (NOT ?2 OR i.is_latest) AND (?3 IS NULL OR i.version = ?3)
+        UNION ALL
+        SELECT i.id, f.quantity * s.quantity
+        FROM flat AS f
+        JOIN inventory_sets AS s ON s.inventory_id = f.inventory_id
+        JOIN inventories AS i ON i.set_num = s.set_num AND i.is_latest
+        UNION ALL
+        SELECT i.id, f.quantity * m.quantity
+        FROM flat AS f
+        JOIN inventory_minifigs AS m ON m.inventory_id = f.inventory_id
+        JOIN inventories AS i ON i.fig_num = m.fig_num AND i.is_latest
+    )
+    SELECT
+        ip.part_num,
+        ip.color_id,
+        pc.name,
+        SUM(ip.quantity * f.quantity)
+    FROM flat AS f
+    JOIN inventory_parts AS ip ON ip.inventory_id = f.inventory_id
+    JOIN parts AS p ON p.part_num = ip.part_num
+    JOIN part_categories AS pc ON pc.id = p.part_cat_id
+    WHERE ?1 OR NOT ip.is_spare
+    GROUP BY ip.part_num, ip.color_id
+    ORDER BY pc.name, ip.part_num, ip.color_id
+"#;
+
+impl Database {
+    /// Computes the loose parts obtained by parting out the given sets, using
+    /// the given versions of their inventories.
+    pub fn collection_parts(
+        &mut self,
+        sets: &[SetListEntry],
+        version: InventoryVersion,
+        include_spares: bool,
+    ) -> anyhow::Result<CollectionParts> {
+        let (latest_only, exact_version) = super::version_params(version);
+        let tx = self.conn.transaction()?;
+        tx.execute_batch(
+            r#"
+            CREATE TEMP TABLE IF NOT EXISTS owned_sets (
+                set_num TEXT NOT NULL,
+                quantity INTEGER NOT NULL
+            );
+            DELETE FROM temp.owned_sets;
+            "#,
+        )?;
+        {
+            let mut stmt =
+                tx.prepare("INSERT INTO temp.owned_sets (set_num, quantity) VALUES (?, ?)")?;
+            for set in sets {
+                stmt.execute(params![set.set_num, set.quantity])?;
+            }
+        }
+
+        let unknown_sets = tx
+            .prepare(
+                r#"
+                SELECT DISTINCT set_num
+                FROM temp.owned_sets
+                WHERE set_num NOT IN (
+                    SELECT set_num
+                    FROM inventories
+                    WHERE set_num IS NOT NULL AND (?1 IS NULL OR version = ?1)
+                )
+                ORDER BY set_num
+                "#,
+            )?
+            .query_map(params![exact_version], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        let parts = tx
+            .prepare(COLLECTION_PARTS_QUERY)?
+            .query_map(params![include_spares, latest_only, exact_version], |row| {
+                Ok(CollectionPart {
+                    part_num: row.get(0)?,
+                    color_id: row.get(1)?,
+                    category: row.get(2)?,
+                    quantity: row.get(3)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        // Nothing is persisted: the temporary table is only needed for this
+        // query.
+        tx.rollback()?;
+        Ok(CollectionParts {
+            parts,
+            unknown_sets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CollectionPart;
+    use crate::{
+        database::tests::open_fixture, rebrickable::list::SetListEntry, types::InventoryVersion,
+    };
+
+    fn part(part_num: &str, color_id: i32, category: &str, quantity: u32) -> CollectionPart {
+        CollectionPart {
+            part_num: part_num.to_owned(),
+            color_id,
+            category: category.to_owned(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn collection_parts() -> anyhow::Result<()> {
+        let mut db = open_fixture()?;
+        let sets = [
+            SetListEntry {
+                set_num: "1000-1".to_owned(),
+                quantity: 2,
+            },
+            SetListEntry {
+                set_num: "9999-1".to_owned(),
+                quantity: 1,
+            },
+        ];
+
+        let parts = db.collection_parts(&sets, InventoryVersion::Latest, false)?;
+        assert_eq!(parts.unknown_sets, ["9999-1"]);
+        assert_eq!(
+            parts.parts,
+            [
+                part("3001", 4, "Bricks", 8),
+                part("3003", 15, "Bricks", 6),
+                part("3626c", 14, "Minifig Heads", 2),
+                part("973pr0001", 1, "Minifig Upper Body", 2),
+            ]
+        );
+
+        let parts = db.collection_parts(&sets, InventoryVersion::Latest, true)?;
+        assert_eq!(parts.parts[0], part("3001", 4, "Bricks", 10));
+        Ok(())
+    }
+
+    #[test]
+    fn collection_parts_versions() -> anyhow::Result<()> {
+        let mut db = open_fixture()?;
+        let sets = [
+            SetListEntry {
+                set_num: "1000-1".to_owned(),
+                quantity: 1,
+            },
+            SetListEntry {
+                set_num: "1001-1".to_owned(),
+                quantity: 1,
+            },
+        ];
+        let mut bricks = |version| -> anyhow::Result<_> {
+            let parts = db.collection_parts(&sets, version, false)?;
+            let quantity = parts
+                .parts
+                .iter()
+                .find(|part| part.part_num == "3001")
+                .map_or(0, |part| part.quantity);
+            Ok((quantity, parts.unknown_sets))
+        };
+
+        assert_eq!(bricks(InventoryVersion::Latest)?, (4, vec![]));
+        assert_eq!(bricks(InventoryVersion::All)?, (9, vec![]));
+        assert_eq!(bricks(InventoryVersion::Exact(1))?, (5, vec![]));
+        assert_eq!(
+            bricks(InventoryVersion::Exact(2))?,
+            (4, vec!["1001-1".to_owned()])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{Connection, params};
+
+use super::Database;
+
+/// The journal mode of a finalized database.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum JournalMode {
+    /// A rollback journal, deleted at the end of each transaction.
+    #[default]
+    Delete,
+    /// A write-ahead log, letting readers run concurrently with a writer.
+    Wal,
+}
+
+impl JournalMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Delete => "delete",
+            Self::Wal => "wal",
+        }
+    }
+}
+
+/// Fails if the database at `path` is in use in WAL mode.
+///
+/// Readers of a replaced database keep reading the previous file, except in
+/// WAL mode: they share its `-wal` and `-shm` files, which would not match the
+/// new one.
+pub fn ensure_replaceable(path: &Path) -> anyhow::Result<()> {
+    let mut wal_path = PathBuf::from(path).into_os_string();
+    wal_path.push("-wal");
+    if Path::new(&wal_path).exists() {
+        anyhow::bail!(
+            "database {} is in use in WAL mode, close its connections first",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Settings of a finalized database, suited to its readers rather than to a
+/// bulk load.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct FinalizeOptions {
+    pub journal_mode: JournalMode,
+    /// The page size in bytes, a power of two between 512 and 65536.
+    /// Defaults to the current page size.
+    pub page_size: Option<u32>,
+}
+
+impl Database {
+    /// Records a setting or a property of the database in the `metadata`
+    /// table.
+    pub fn set_metadata(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the content of the `metadata` table.
+    pub fn metadata(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        let metadata = self
+            .conn
+            .prepare("SELECT key, value FROM metadata")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(metadata)
+    }
+
+    /// Replaces the database file by a compact copy, with the given settings
+    /// recorded in the `metadata` table.
+    ///
+    /// The copy is written next to the database and renamed over it once
+    /// complete, so readers never see a partial file.
+    pub fn finalize(self, options: FinalizeOptions) -> anyhow::Result<()> {
+        if let Some(page_size) = options.page_size
+            && !(page_size.is_power_of_two() && (512..=65536).contains(&page_size))
+        {
+            anyhow::bail!(
+                "invalid page size {page_size}, expected a power of two between 512 and 65536"
+            );
+        }
+        let path = match self.conn.path() {
+            Some(path) if !path.is_empty() => PathBuf::from(path),
+            _ => anyhow::bail!("cannot finalize an in-memory database"),
+        };
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        if tmp_path.exists() {
+            fs::remove_file(&tmp_path)?;
+        }
+
+        let page_size = match options.page_size {
+            Some(page_size) => {
+                // Only applies to the copy.
+                self.conn.pragma_update(None, "page_size", page_size)?;
+                page_size
+            }
+            None => self
+                .conn
+                .pragma_query_value(None, "page_size", |row| row.get(0))?,
+        };
+        self.set_metadata("journal_mode", options.journal_mode.as_str())?;
+        self.set_metadata("page_size", &page_size.to_string())?;
+        let Some(tmp) = tmp_path.to_str() else {
+            anyhow::bail!("invalid database path {}", tmp_path.display());
+        };
+        self.conn.execute("VACUUM INTO ?1", [tmp])?;
+        drop(self);
+
+        let conn = Connection::open(&tmp_path)?;
+        let journal_mode: String = conn.pragma_update_and_check(
+            None,
+            "journal_mode",
+            options.journal_mode.as_str(),
+            |row| row.get(0),
+        )?;
+        if !journal_mode.eq_ignore_ascii_case(options.journal_mode.as_str()) {
+            anyhow::bail!(
+                "failed to switch to journal mode {}",
+                options.journal_mode.as_str()
+            );
+        }
+        conn.close().map_err(|(_, err)| err)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+    use tempfile::tempdir;
+
+    use super::{FinalizeOptions, JournalMode};
+    use crate::database::Database;
+
+    #[test]
+    fn finalize() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("rebrickable.db");
+        let db = Database::open(&path)?;
+        db.finalize(FinalizeOptions {
+            journal_mode: JournalMode::Wal,
+            page_size: Some(8192),
+        })?;
+
+        assert!(!dir.path().join("rebrickable.db.tmp").exists());
+        let conn = Connection::open(&path)?;
+        let journal_mode: String =
+            conn.pragma_query_value(None, "journal_mode", |row| row.get(0))?;
+        assert_eq!(journal_mode, "wal");
+        let page_size: u32 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+        assert_eq!(page_size, 8192);
+        let metadata: Vec<(String, String)> = conn
+            .prepare("SELECT key, value FROM metadata ORDER BY key")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            metadata,
+            [
+                ("journal_mode".to_owned(), "wal".to_owned()),
+                ("page_size".to_owned(), "8192".to_owned()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn finalize_invalid_page_size() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let db = Database::open(dir.path().join("rebrickable.db"))?;
+        let options = FinalizeOptions {
+            page_size: Some(1000),
+            ..FinalizeOptions::default()
+        };
+        assert!(db.finalize(options).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+-- A tiny excerpt-like dataset for tests.
+
+INSERT INTO colors (id, name, rgb, is_trans, num_parts, num_sets, first_year, last_year) VALUES
+    (0, 'Black', '05131d', 0, 0, 0, 1957, 2024),
+    (1, 'Blue', '0055bf', 0, 1, 1, 1950, 2024),
+    (4, 'Red', 'c91a09', 0, 1, 1, 1950, 2024),
+    (14, 'Yellow', 'f2cd37', 0, 1, 1, 1950, 2024),
+    (15, 'White', 'ffffff', 0, 1, 1, 1950, 2024);
+
+INSERT INTO part_categories (id, name) VALUES
+    (11, 'Bricks'),
+    (59, 'Minifig Heads'),
+    (60, 'Minifig Upper Body'),
+    (61, 'Minifig Lower Body');
+
+INSERT INTO parts (part_num, name, part_cat_id, part_material) VALUES
+    ('3001', 'Brick 2 x 4', 11, 'plastic'),
+    ('3001a', 'Brick 2 x 4 without Cross Supports', 11, 'plastic'),
+    ('3001b', 'Brick 2 x 4 with Hollow Studs', 11, 'plastic'),
+    ('3003', 'Brick 2 x 2', 11, 'plastic'),
+    ('3626c', 'Minifig Head', 59, 'plastic'),
+    ('3626cpr0001', 'Minifig Head with Standard Grin Pattern', 59, 'plastic'),
+    ('973', 'Torso Plain', 60, 'plastic'),
+    ('973pr0001', 'Torso with Fire Logo Print', 60, 'plastic'),
+    ('73200', 'Legs Assembly', 61, 'plastic'),
+    ('970', 'Hips', 61, 'plastic'),
+    ('971', 'Left Leg', 61, 'plastic');
+
+INSERT INTO part_relationships (rel_type, child_part_num, parent_part_num) VALUES
+    ('mold', '3001a', '3001'),
+    ('alternate', '3001b', '3001a'),
+    ('print', '3626cpr0001', '3626c'),
+    ('pattern', '973pr0001', '973'),
+    ('subpart', '970', '73200'),
+    ('subpart', '971', '73200');
+
+INSERT INTO elements (element_id, part_num, color_id, design_id) VALUES
+    ('300121', '3001', 4, 3001),
+    ('300301', '3003', 15, 3003),
+    ('3626024', '3626c', 14, 3626);
+
+INSERT INTO minifigs (fig_num, name, num_parts, img_url) VALUES
+    ('fig-000001', 'Firefighter', 2, 'https://cdn.rebrickable.com/media/sets/fig-000001.jpg');
+
+INSERT INTO themes (id, name, parent_id) VALUES
+    (1, 'Town', NULL),
+    (2, 'Fire', 1),
+    (3, 'Space', NULL);
+
+INSERT INTO sets (set_num, name, year, theme_id, num_parts, img_url) VALUES
+    ('1000-1', 'Fire Station', 2020, 2, 10, 'https://cdn.rebrickable.com/media/sets/1000-1.jpg'),
+    ('1001-1', 'Fire Cart', 2020, 2, 3, 'https://cdn.rebrickable.com/media/sets/1001-1.jpg');
+
+INSERT INTO inventories (id, version, is_latest, kind, set_num, fig_num) VALUES
+    (1, 1, 0, 'set', '1000-1', NULL),
+    (2, 2, 1, 'set', '1000-1', NULL),
+    (3, 1, 1, 'set', '1001-1', NULL),
+    (4, 1, 1, 'minifig', NULL, 'fig-000001');
+
+INSERT INTO inventory_parts (inventory_id, part_num, color_id, quantity, is_spare, img_url) VALUES
+    (1, '3001', 4, 5, 0, NULL),
+    (2, '3001', 4, 4, 0, 'https://cdn.rebrickable.com/media/parts/elements/300121.jpg'),
+    (2, '3001', 4, 1, 1, 'https://cdn.rebrickable.com/media/parts/elements/300121.jpg'),
+    (3, '3003', 15, 3, 0, NULL),
+    (4, '3626c', 14, 1, 0, NULL),
+    (4, '973pr0001', 1, 1, 0, NULL);
+
+INSERT INTO inventory_minifigs (inventory_id, fig_num, quantity) VALUES
+    (2, 'fig-000001', 1);
+
+INSERT INTO inventory_sets (inventory_id, set_num, quantity) VALUES
+    (2, '1001-1', 1);
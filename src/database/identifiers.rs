@@ -0,0 +1,91 @@
+use super::Database;
+
+/// The kinds of identifiers that can be completed.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum IdentifierKind {
+    /// Set numbers.
+    Set,
+    /// Part numbers.
+    Part,
+    /// Colour names, matched without regard to case.
+    Color,
+}
+
+impl Database {
+    /// Returns up to `limit` identifiers starting with a prefix, in order,
+    /// with the names of what they identify.
+    pub fn identifiers(
+        &self,
+        kind: IdentifierKind,
+        prefix: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        // The range lets the primary keys be used for the prefix.
+        let sql = match kind {
+            IdentifierKind::Set => {
+                r#"
+                SELECT set_num, name
+                FROM sets
+                WHERE set_num >= ?1 AND set_num < ?1 || char(1114111)
+                ORDER BY set_num
+                LIMIT ?2
+                "#
+            }
+            IdentifierKind::Part => {
+                r#"
+                SELECT part_num, name
+                FROM parts
+                WHERE part_num >= ?1 AND part_num < ?1 || char(1114111)
+                ORDER BY part_num
+                LIMIT ?2
+                "#
+            }
+            IdentifierKind::Color => {
+                r#"
+                SELECT name, rgb
+                FROM colors
+                WHERE lower(substr(name, 1, length(?1))) = lower(?1)
+                ORDER BY name
+                LIMIT ?2
+                "#
+            }
+        };
+        let identifiers = self
+            .conn
+            .prepare(sql)?
+            .query_map((prefix, limit as i64), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(identifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IdentifierKind;
+    use crate::database::tests::open_fixture;
+
+    #[test]
+    fn identifiers() -> anyhow::Result<()> {
+        let db = open_fixture()?;
+        assert_eq!(
+            db.identifiers(IdentifierKind::Set, "100", 10)?,
+            [
+                ("1000-1".to_owned(), "Fire Station".to_owned()),
+                ("1001-1".to_owned(), "Fire Cart".to_owned())
+            ]
+        );
+        assert_eq!(db.identifiers(IdentifierKind::Set, "1001", 10)?.len(), 1);
+        assert_eq!(db.identifiers(IdentifierKind::Set, "100", 1)?.len(), 1);
+        let parts = db.identifiers(IdentifierKind::Part, "3001", 10)?;
+        let part_nums: Vec<_> = parts
+            .iter()
+            .map(|(part_num, _)| part_num.as_str())
+            .collect();
+        assert_eq!(part_nums, ["3001", "3001a", "3001b"]);
+        assert_eq!(
+            db.identifiers(IdentifierKind::Color, "re", 10)?,
+            [("Red".to_owned(), "c91a09".to_owned())]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+use rusqlite::params;
+
+use super::Database;
+use crate::types::InventoryVersion;
+
+/// The kinds of images to select.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, clap::ValueEnum)]
+pub enum ImageKind {
+    /// The images of the sets.
+    Set,
+    /// The images of the minifigures in the sets.
+    Minifig,
+    /// The images of the parts in the sets, in their colour.
+    Part,
+}
+
+/// Selects the sets whose images, and those of their contents, are fetched.
+///
+/// Empty criteria select every set.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ImageFilter {
+    /// The themes of the sets, including their sub-themes.
+    pub themes: Vec<i32>,
+    pub years: Vec<i32>,
+    pub set_nums: Vec<String>,
+    pub kinds: Vec<ImageKind>,
+    /// The versions of the inventories of the sets to take contents from.
+    pub inventory_version: InventoryVersion,
+}
+
+/// An image stored in the local cache.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CachedImage {
+    pub url: String,
+    pub sha256: String,
+    pub path: String,
+    pub size: i64,
+    /// When the image was fetched, in seconds since the Unix epoch.
+    pub fetched_at: i64,
+}
+
+// The criteria are bound as JSON arrays, or NULL when empty. Contents come
+// from the selected versions of the inventories of the selected sets.
+const IMAGE_URLS_QUERY: &str = r#"
+    WITH RECURSIVE
+    selected_themes (id) AS (
+        SELECT id
+        FROM themes
+        WHERE id IN (SELECT value FROM json_each(?1))
+        UNION
+        SELECT t.id
+        FROM themes AS t
+        JOIN selected_themes AS s ON t.parent_id = s.id
+    ),
+    selected_sets (set_num, img_url) AS (
+        SELECT set_num, img_url
+        FROM sets
+        WHERE (?1 IS NULL OR theme_id IN selected_themes)
+            AND (?2 IS NULL OR year IN (SELECT value FROM json_each(?2)))
+            AND (?3 IS NULL OR set_num IN (SELECT value FROM json_each(?3)))
+    ),
+    selected_inventories (id) AS (
+        SELECT i.id
+        FROM inventories AS i
+        JOIN selected_sets AS s ON s.set_num = i.set_num
+        WHERE (NOT ?7 OR i.is_latest) AND (?8 IS NULL OR i.version = ?8)
+    ),
+    urls (url) AS (
+        SELECT img_url
+        FROM selected_sets
+        WHERE ?4
+        UNION
+        SELECT m.img_url
+        FROM selected_inventories AS i
+        JOIN inventory_minifigs AS im ON im.inventory_id = i.id
+        JOIN minifigs AS m ON m.fig_num = im.fig_num
+        WHERE ?5
+        UNION
+        SELECT ip.img_url
+        FROM selected_inventories AS i
+        JOIN inventory_parts AS ip ON ip.inventory_id = i.id
+        WHERE ?6
+    )
+    SELECT url
+    FROM urls
+    WHERE url IS NOT NULL AND url <> ''
+    ORDER BY url
+"#;
+
+impl Database {
+    /// Lists the distinct URLs of the images selected by a filter.
+    pub fn image_urls(&self, filter: &ImageFilter) -> anyhow::Result<Vec<String>> {
+        fn json_array<T: serde::Serialize>(values: &[T]) -> anyhow::Result<Option<String>> {
+            Ok(if values.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(values)?)
+            })
+        }
+        let kind = |kind| filter.kinds.is_empty() || filter.kinds.contains(&kind);
+        let (latest_only, exact_version) = super::version_params(filter.inventory_version);
+        let urls = self
+            .conn
+            .prepare(IMAGE_URLS_QUERY)?
+            .query_map(
+                params![
+                    json_array(&filter.themes)?,
+                    json_array(&filter.years)?,
+                    json_array(&filter.set_nums)?,
+                    kind(ImageKind::Set),
+                    kind(ImageKind::Minifig),
+                    kind(ImageKind::Part),
+                    latest_only,
+                    exact_version,
+                ],
+                |row| row.get(0),
+            )?
+            .collect::<Result<_, _>>()?;
+        Ok(urls)
+    }
+
+    /// Returns the images recorded in the `images` table, by URL.
+    pub fn cached_images(&self) -> anyhow::Result<BTreeMap<String, CachedImage>> {
+        let images = self
+            .conn
+            .prepare("SELECT url, sha256, path, size, fetched_at FROM images")?
+            .query_map([], |row| {
+                let image = CachedImage {
+                    url: row.get(0)?,
+                    sha256: row.get(1)?,
+                    path: row.get(2)?,
+                    size: row.get(3)?,
+                    fetched_at: row.get(4)?,
+                };
+                Ok((image.url.clone(), image))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(images)
+    }
+
+    /// Records an image in the `images` table, replacing any previous record
+    /// of its URL.
+    pub fn record_image(&self, image: &CachedImage) -> anyhow::Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT OR REPLACE INTO images (url, sha256, path, size, fetched_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+            params![
+                image.url,
+                image.sha256,
+                image.path,
+                image.size,
+                image.fetched_at
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedImage, ImageFilter, ImageKind};
+    use crate::{database::tests::open_fixture, types::InventoryVersion};
+
+    #[test]
+    fn image_urls() -> anyhow::Result<()> {
+        let db = open_fixture()?;
+        let urls = |filter| -> anyhow::Result<Vec<String>> {
+            let urls = db.image_urls(&filter)?;
+            Ok(urls
+                .into_iter()
+                .map(|url| url.rsplit('/').next().unwrap_or_default().to_owned())
+                .collect())
+        };
+
+        assert_eq!(
+            urls(ImageFilter::default())?,
+            ["300121.jpg", "1000-1.jpg", "1001-1.jpg", "fig-000001.jpg"]
+        );
+        // Sub-themes are included.
+        let town = ImageFilter {
+            themes: vec![1],
+            kinds: vec![ImageKind::Set],
+            ..ImageFilter::default()
+        };
+        assert_eq!(urls(town)?, ["1000-1.jpg", "1001-1.jpg"]);
+        let space = ImageFilter {
+            themes: vec![3],
+            ..ImageFilter::default()
+        };
+        assert!(urls(space)?.is_empty());
+        let cart = ImageFilter {
+            years: vec![2020],
+            set_nums: vec!["1001-1".to_owned()],
+            ..ImageFilter::default()
+        };
+        assert_eq!(urls(cart)?, ["1001-1.jpg"]);
+        // The first version of the station has neither minifigure nor part
+        // images.
+        let station = |inventory_version| ImageFilter {
+            set_nums: vec!["1000-1".to_owned()],
+            kinds: vec![ImageKind::Minifig, ImageKind::Part],
+            inventory_version,
+            ..ImageFilter::default()
+        };
+        assert_eq!(
+            urls(station(InventoryVersion::Latest))?,
+            ["300121.jpg", "fig-000001.jpg"]
+        );
+        assert!(urls(station(InventoryVersion::Exact(1)))?.is_empty());
+        assert_eq!(urls(station(InventoryVersion::All))?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn record_image() -> anyhow::Result<()> {
+        let db = open_fixture()?;
+        let mut image = CachedImage {
+            url: "https://cdn.rebrickable.com/media/sets/1000-1.jpg".to_owned(),
+            sha256: "00".repeat(32),
+            path: "/cache/00/00.jpg".to_owned(),
+            size: 42,
+            fetched_at: 1000,
+        };
+        db.record_image(&image)?;
+        image.size = 43;
+        db.record_image(&image)?;
+        let images = db.cached_images()?;
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[&image.url], image);
+        Ok(())
+    }
+}
@@ -1 +0,0 @@
-CREATE INDEX IF NOT EXISTS inventory_parts_part_num_idx ON inventory_parts(part_num);
@@ -0,0 +1,8 @@
+-- Aggregations over the whole catalog, answered from the indexes alone.
+CREATE INDEX IF NOT EXISTS parts_part_cat_id_idx ON parts(part_cat_id);
+CREATE INDEX IF NOT EXISTS themes_parent_id_idx ON themes(parent_id);
+CREATE INDEX IF NOT EXISTS elements_color_id_idx ON elements(color_id);
+CREATE INDEX IF NOT EXISTS sets_year_theme_id_idx ON sets(year, theme_id, num_parts);
+CREATE INDEX IF NOT EXISTS inventories_latest_idx ON inventories(set_num, fig_num) WHERE is_latest;
+CREATE INDEX IF NOT EXISTS inventory_parts_part_num_color_id_idx
+    ON inventory_parts(part_num, color_id, quantity, is_spare);
@@ -0,0 +1,5 @@
+CREATE INDEX IF NOT EXISTS inventory_parts_part_num_idx ON inventory_parts(part_num);
+-- The inventories of sets and minifigures, joined by `collection parts`,
+-- `images fetch`, `site` and `show set`.
+CREATE INDEX IF NOT EXISTS inventories_set_num_idx ON inventories(set_num);
+CREATE INDEX IF NOT EXISTS inventories_fig_num_idx ON inventories(fig_num);
@@ -0,0 +1,10 @@
+-- Reverse lookups along foreign keys.
+CREATE INDEX IF NOT EXISTS part_relationships_child_part_num_idx ON part_relationships(child_part_num);
+CREATE INDEX IF NOT EXISTS part_relationships_parent_part_num_idx ON part_relationships(parent_part_num);
+CREATE INDEX IF NOT EXISTS elements_part_num_color_id_idx ON elements(part_num, color_id);
+CREATE INDEX IF NOT EXISTS sets_theme_id_idx ON sets(theme_id);
+CREATE INDEX IF NOT EXISTS sets_year_idx ON sets(year);
+CREATE INDEX IF NOT EXISTS inventory_parts_color_id_idx ON inventory_parts(color_id);
+CREATE INDEX IF NOT EXISTS inventory_minifigs_fig_num_idx ON inventory_minifigs(fig_num);
+CREATE INDEX IF NOT EXISTS inventory_sets_set_num_idx ON inventory_sets(set_num);
+CREATE INDEX IF NOT EXISTS part_families_family_part_num_idx ON part_families(family_part_num);
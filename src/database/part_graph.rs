@@ -0,0 +1,96 @@
+use rusqlite::{OptionalExtension, params};
+
+use super::Database;
+use crate::{
+    part_graph::PartGraph, rebrickable::record::PartRelationship, types::PartRelationType,
+};
+
+impl Database {
+    /// Loads the relationships between parts.
+    pub fn part_graph(&self) -> anyhow::Result<PartGraph> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT rel_type, child_part_num, parent_part_num FROM part_relationships")?;
+        let relationships = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .map(|row| {
+                let (rel_type, child_part_num, parent_part_num) = row?;
+                Ok(PartRelationship {
+                    rel_type: rel_type.parse()?,
+                    child_part_num,
+                    parent_part_num,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(PartGraph::new(relationships))
+    }
+
+    pub fn part_name(&self, part_num: &str) -> anyhow::Result<Option<String>> {
+        let name = self
+            .conn
+            .query_row(
+                "SELECT name FROM parts WHERE part_num = ?",
+                [part_num],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(name)
+    }
+
+    /// Fills the `part_families` table, grouping parts through relationships
+    /// of the given types.
+    pub fn create_part_families(&mut self, rel_types: &[PartRelationType]) -> anyhow::Result<()> {
+        let classes = self.part_graph()?.equivalence_classes(rel_types);
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM part_families", [])?;
+        tx.execute(
+            "INSERT INTO part_families (part_num, family_part_num) SELECT part_num, part_num FROM parts",
+            [],
+        )?;
+        {
+            let mut stmt =
+                tx.prepare("UPDATE part_families SET family_part_num = ? WHERE part_num = ?")?;
+            for (family_part_num, part_nums) in &classes {
+                for part_num in part_nums {
+                    stmt.execute(params![family_part_num, part_num])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{database::tests::open_fixture, part_graph::DEFAULT_EQUIVALENCE_TYPES};
+
+    #[test]
+    fn part_families() -> anyhow::Result<()> {
+        let mut db = open_fixture()?;
+        db.create_part_families(DEFAULT_EQUIVALENCE_TYPES)?;
+        let families: Vec<(String, String)> = db
+            .conn
+            .prepare(
+                "SELECT part_num, family_part_num FROM part_families \
+                 WHERE part_num GLOB '3001*' ORDER BY part_num",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            families,
+            [
+                ("3001".to_owned(), "3001".to_owned()),
+                ("3001a".to_owned(), "3001".to_owned()),
+                ("3001b".to_owned(), "3001".to_owned()),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use rusqlite::{Connection, ToSql, types::Value};
+
+use super::Database;
+
+/// The result of an arbitrary query.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Database {
+    /// Runs an arbitrary SQL statement, returning its columns and rows.
+    pub fn query(&self, sql: &str, params: &[&dyn ToSql]) -> anyhow::Result<QueryResult> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_owned).collect();
+        let rows = stmt
+            .query_map(params, |row| {
+                (0..columns.len())
+                    .map(|i| row.get(i))
+                    .collect::<Result<_, _>>()
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(QueryResult { columns, rows })
+    }
+}
+
+/// Returns the columns of the tables and views of the schema, by name.
+pub fn schema_columns() -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(include_str!("schema.sql"))?;
+    conn.execute_batch(include_str!("views.sql"))?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT s.name, c.name
+        FROM sqlite_schema AS s, pragma_table_info(s.name) AS c
+        WHERE s.type IN ('table', 'view')
+        ORDER BY s.name, c.cid
+        "#,
+    )?;
+    let mut columns: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        columns.entry(row.get(0)?).or_default().push(row.get(1)?);
+    }
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::types::Value;
+
+    use crate::database::tests::open_fixture;
+
+    #[test]
+    fn query() -> anyhow::Result<()> {
+        let db = open_fixture()?;
+        let result = db.query(
+            "SELECT set_num, year, NULL AS missing FROM sets WHERE year = ?1 ORDER BY set_num",
+            &[&2020],
+        )?;
+        assert_eq!(result.columns, ["set_num", "year", "missing"]);
+        assert_eq!(
+            result.rows[0],
+            [
+                Value::Text("1000-1".to_owned()),
+                Value::Integer(2020),
+                Value::Null
+            ]
+        );
+        assert_eq!(result.rows.len(), 2);
+
+        let columns = super::schema_columns()?;
+        assert_eq!(columns["themes"], ["id", "name", "parent_id"]);
+        assert!(columns.contains_key("set_parts_flat"));
+        Ok(())
+    }
+}
@@ -84,12 +84,34 @@ CREATE TABLE IF NOT EXISTS sets (
     img_url TEXT NOT NULL
 ) STRICT;
 
--- NOTE: `set_num` references either `sets.set_num` or `minifigs.fig_num`. In
--- the latter case, it starts with `fig-`.
+-- NOTE: An inventory describes either a set or a minifigure, depending on
+-- `kind`. The dumps have a single `set_num` column for both, minifigure
+-- numbers starting with `fig-`. `is_latest` is computed once all the
+-- inventories are loaded.
 CREATE TABLE IF NOT EXISTS inventories (
     id INTEGER PRIMARY KEY,
     version INTEGER NOT NULL,
-    set_num TEXT NOT NULL
+    is_latest INTEGER NOT NULL DEFAULT 0
+        CHECK (is_latest IN (0, 1)),
+    kind TEXT NOT NULL
+        CHECK (kind IN ('set', 'minifig')),
+    set_num TEXT
+        REFERENCES sets(set_num),
+    fig_num TEXT
+        REFERENCES minifigs(fig_num),
+    CHECK ((set_num IS NOT NULL) = (kind = 'set')),
+    CHECK ((fig_num IS NOT NULL) = (kind = 'minifig'))
+) STRICT;
+
+-- NOTE: Rebrickable has inventories of unknown sets and minifigures, which
+-- cannot be stored in `inventories`. They are moved here when loading, and
+-- their contents are skipped.
+CREATE TABLE IF NOT EXISTS skipped_inventories (
+    id INTEGER PRIMARY KEY,
+    version INTEGER NOT NULL,
+    kind TEXT NOT NULL
+        CHECK (kind IN ('set', 'minifig')),
+    item_num TEXT NOT NULL
 ) STRICT;
 
 CREATE TABLE IF NOT EXISTS inventory_parts (
@@ -126,3 +148,103 @@ CREATE TABLE IF NOT EXISTS inventory_sets (
         CHECK (quantity >= 1),
     UNIQUE (inventory_id, set_num)
 ) STRICT;
+
+-- NOTE: External identifiers are fetched from the Rebrickable API rather than
+-- from the CSV dumps, and may be missing.
+CREATE TABLE IF NOT EXISTS color_external_ids (
+    color_id INTEGER NOT NULL
+        REFERENCES colors(id),
+    source TEXT NOT NULL,
+    external_id INTEGER NOT NULL,
+    name TEXT,
+    UNIQUE (color_id, source, external_id)
+) STRICT;
+
+CREATE TABLE IF NOT EXISTS part_external_ids (
+    part_num TEXT NOT NULL
+        REFERENCES parts(part_num),
+    source TEXT NOT NULL,
+    external_id TEXT NOT NULL,
+    UNIQUE (part_num, source, external_id)
+) STRICT;
+
+-- NOTE: User lists are synchronized from the Rebrickable API. Entries that
+-- reference sets, parts or colours missing from the dumps are skipped.
+CREATE TABLE IF NOT EXISTS user_setlists (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    is_buildable INTEGER NOT NULL
+        CHECK (is_buildable IN (0, 1))
+) STRICT;
+
+CREATE TABLE IF NOT EXISTS user_setlist_sets (
+    setlist_id INTEGER NOT NULL
+        REFERENCES user_setlists(id) ON DELETE CASCADE,
+    set_num TEXT NOT NULL
+        REFERENCES sets(set_num),
+    quantity INTEGER NOT NULL
+        CHECK (quantity >= 1),
+    include_spares INTEGER NOT NULL
+        CHECK (include_spares IN (0, 1)),
+    UNIQUE (setlist_id, set_num)
+) STRICT;
+
+CREATE TABLE IF NOT EXISTS user_partlists (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    is_buildable INTEGER NOT NULL
+        CHECK (is_buildable IN (0, 1))
+) STRICT;
+
+CREATE TABLE IF NOT EXISTS user_partlist_parts (
+    partlist_id INTEGER NOT NULL
+        REFERENCES user_partlists(id) ON DELETE CASCADE,
+    part_num TEXT NOT NULL
+        REFERENCES parts(part_num),
+    color_id INTEGER NOT NULL
+        REFERENCES colors(id),
+    quantity INTEGER NOT NULL
+        CHECK (quantity >= 1),
+    UNIQUE (partlist_id, part_num, color_id)
+) STRICT;
+
+-- NOTE: `set_num` is the set or minifigure the part was lost from.
+CREATE TABLE IF NOT EXISTS user_lost_parts (
+    id INTEGER PRIMARY KEY,
+    set_num TEXT NOT NULL,
+    part_num TEXT NOT NULL
+        REFERENCES parts(part_num),
+    color_id INTEGER NOT NULL
+        REFERENCES colors(id),
+    is_spare INTEGER NOT NULL
+        CHECK (is_spare IN (0, 1)),
+    quantity INTEGER NOT NULL
+        CHECK (quantity >= 1)
+) STRICT;
+
+-- NOTE: Parts are grouped into families of interchangeable parts, through mold
+-- and alternate relationships. Each family is named after its smallest part
+-- number, and parts without such relationships are alone in their family.
+CREATE TABLE IF NOT EXISTS part_families (
+    part_num TEXT PRIMARY KEY
+        REFERENCES parts(part_num),
+    family_part_num TEXT NOT NULL
+        REFERENCES parts(part_num)
+) STRICT;
+
+-- NOTE: Settings and properties of the database, such as the version of the
+-- tool that created it.
+CREATE TABLE IF NOT EXISTS metadata (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+) STRICT;
+
+-- NOTE: Images fetched into the local cache by `images fetch`, by URL. Files
+-- are named after their SHA-256 hash, so identical images are stored once.
+CREATE TABLE IF NOT EXISTS images (
+    url TEXT PRIMARY KEY,
+    sha256 TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    fetched_at INTEGER NOT NULL
+) STRICT;
@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+
+use rusqlite::{OptionalExtension, Params, Transaction};
+
+use super::Database;
+use crate::rebrickable::api::users::UserLists;
+
+/// The outcome of the synchronization of a table.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct SyncStats {
+    /// The number of rows in the table after the synchronization.
+    pub rows: usize,
+    pub added: usize,
+    pub removed: usize,
+    /// The number of entries skipped because they reference sets, parts or
+    /// colours that are not in the database.
+    pub skipped: usize,
+}
+
+impl Database {
+    /// Synchronizes the lists of a user, returning statistics by table.
+    ///
+    /// Entries are upserted by key, and rows that are no longer in the lists
+    /// are removed, so this can be re-run to update the tables in place.
+    pub fn sync_user_lists(
+        &mut self,
+        lists: &UserLists,
+    ) -> anyhow::Result<Vec<(&'static str, SyncStats)>> {
+        let tx = self.conn.transaction()?;
+        let mut stats = Vec::new();
+
+        stats.push((
+            "user_setlists",
+            sync_table(
+                &tx,
+                "user_setlists",
+                r#"
+                INSERT INTO user_setlists (id, name, is_buildable)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT (id) DO UPDATE
+                SET name = excluded.name, is_buildable = excluded.is_buildable
+                RETURNING rowid
+                "#,
+                lists.set_lists.iter().map(|(list, _)| list),
+                |list| (list.id, list.name.as_str(), list.is_buildable),
+            )?,
+        ));
+        stats.push((
+            "user_setlist_sets",
+            sync_table(
+                &tx,
+                "user_setlist_sets",
+                r#"
+                INSERT INTO user_setlist_sets (setlist_id, set_num, quantity, include_spares)
+                SELECT ?1, ?2, ?3, ?4
+                WHERE EXISTS (SELECT 1 FROM sets WHERE set_num = ?2)
+                ON CONFLICT (setlist_id, set_num) DO UPDATE
+                SET quantity = excluded.quantity, include_spares = excluded.include_spares
+                RETURNING rowid
+                "#,
+                lists
+                    .set_lists
+                    .iter()
+                    .flat_map(|(list, sets)| sets.iter().map(move |set| (list.id, set))),
+                |(list_id, set)| {
+                    (
+                        list_id,
+                        set.set.set_num.as_str(),
+                        set.quantity,
+                        set.include_spares,
+                    )
+                },
+            )?,
+        ));
+        stats.push((
+            "user_partlists",
+            sync_table(
+                &tx,
+                "user_partlists",
+                r#"
+                INSERT INTO user_partlists (id, name, is_buildable)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT (id) DO UPDATE
+                SET name = excluded.name, is_buildable = excluded.is_buildable
+                RETURNING rowid
+                "#,
+                lists.part_lists.iter().map(|(list, _)| list),
+                |list| (list.id, list.name.as_str(), list.is_buildable),
+            )?,
+        ));
+        stats.push((
+            "user_partlist_parts",
+            sync_table(
+                &tx,
+                "user_partlist_parts",
+                r#"
+                INSERT INTO user_partlist_parts (partlist_id, part_num, color_id, quantity)
+                SELECT ?1, ?2, ?3, ?4
+                WHERE EXISTS (SELECT 1 FROM parts WHERE part_num = ?2)
+                    AND EXISTS (SELECT 1 FROM colors WHERE id = ?3)
+                ON CONFLICT (partlist_id, part_num, color_id) DO UPDATE
+                SET quantity = excluded.quantity
+                RETURNING rowid
+                "#,
+                lists
+                    .part_lists
+                    .iter()
+                    .flat_map(|(list, parts)| parts.iter().map(move |part| (list.id, part))),
+                |(list_id, part)| {
+                    (
+                        list_id,
+                        part.part.part_num.as_str(),
+                        part.color.id,
+                        part.quantity,
+                    )
+                },
+            )?,
+        ));
+        stats.push((
+            "user_lost_parts",
+            sync_table(
+                &tx,
+                "user_lost_parts",
+                r#"
+                INSERT INTO user_lost_parts (id, set_num, part_num, color_id, is_spare, quantity)
+                SELECT ?1, ?2, ?3, ?4, ?5, ?6
+                WHERE EXISTS (SELECT 1 FROM parts WHERE part_num = ?3)
+                    AND EXISTS (SELECT 1 FROM colors WHERE id = ?4)
+                ON CONFLICT (id) DO UPDATE
+                SET
+                    set_num = excluded.set_num,
+                    part_num = excluded.part_num,
+                    color_id = excluded.color_id,
+                    is_spare = excluded.is_spare,
+                    quantity = excluded.quantity
+                RETURNING rowid
+                "#,
+                lists.lost_parts.iter(),
+                |lost| {
+                    (
+                        lost.lost_part_id,
+                        lost.inv_part.set_num.as_str(),
+                        lost.inv_part.part.part_num.as_str(),
+                        lost.inv_part.color.id,
+                        lost.inv_part.is_spare,
+                        lost.lost_quantity,
+                    )
+                },
+            )?,
+        ));
+
+        tx.commit()?;
+        Ok(stats)
+    }
+}
+
+/// Upserts rows with a statement returning their `rowid`, then removes the
+/// rows of the table that were not upserted.
+fn sync_table<T, I, P, F>(
+    tx: &Transaction,
+    table: &str,
+    upsert_stmt: &str,
+    rows: I,
+    to_params: F,
+) -> anyhow::Result<SyncStats>
+where
+    I: IntoIterator<Item = T>,
+    P: Params,
+    F: Fn(T) -> P,
+{
+    let mut stale: HashSet<i64> = tx
+        .prepare(&format!("SELECT rowid FROM {table}"))?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+    let mut stats = SyncStats::default();
+    let mut stmt = tx.prepare(upsert_stmt)?;
+    let mut synced = HashSet::new();
+    for row in rows {
+        match stmt
+            .query_row(to_params(row), |row| row.get::<_, i64>(0))
+            .optional()?
+        {
+            Some(rowid) => {
+                let is_first = synced.insert(rowid);
+                if !stale.remove(&rowid) && is_first {
+                    stats.added += 1;
+                }
+            }
+            None => stats.skipped += 1,
+        }
+    }
+
+    let mut delete = tx.prepare(&format!("DELETE FROM {table} WHERE rowid = ?"))?;
+    for rowid in &stale {
+        delete.execute([rowid])?;
+    }
+    stats.removed = stale.len();
+    stats.rows = synced.len();
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::tests::open_fixture,
+        rebrickable::api::users::{
+            ApiColorRef, ApiPartList, ApiPartListPart, ApiPartRef, ApiSetList, ApiSetListSet,
+            ApiSetRef, UserLists,
+        },
+    };
+
+    fn set_list_set(set_num: &str, quantity: i32) -> ApiSetListSet {
+        ApiSetListSet {
+            quantity,
+            include_spares: true,
+            set: ApiSetRef {
+                set_num: set_num.to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn sync_user_lists() -> anyhow::Result<()> {
+        let mut db = open_fixture()?;
+        let owned = ApiSetList {
+            id: 1,
+            name: "Owned".to_owned(),
+            is_buildable: true,
+        };
+        let mut lists = UserLists {
+            set_lists: vec![(
+                owned.clone(),
+                vec![set_list_set("1000-1", 1), set_list_set("9999-1", 1)],
+            )],
+            part_lists: vec![(
+                ApiPartList {
+                    id: 7,
+                    name: "Wanted".to_owned(),
+                    is_buildable: false,
+                },
+                vec![ApiPartListPart {
+                    quantity: 4,
+                    part: ApiPartRef {
+                        part_num: "3001".to_owned(),
+                    },
+                    color: ApiColorRef { id: 4 },
+                }],
+            )],
+            lost_parts: Vec::new(),
+        };
+
+        let stats = db.sync_user_lists(&lists)?;
+        let (table, sets) = stats[1];
+        assert_eq!(table, "user_setlist_sets");
+        assert_eq!(
+            (sets.rows, sets.added, sets.removed, sets.skipped),
+            (1, 1, 0, 1)
+        );
+
+        // Re-running updates rows in place, and removes those that are gone.
+        lists.set_lists = vec![(owned, vec![set_list_set("1001-1", 3)])];
+        lists.part_lists.clear();
+        let stats = db.sync_user_lists(&lists)?;
+        let (_, sets) = stats[1];
+        assert_eq!(
+            (sets.rows, sets.added, sets.removed, sets.skipped),
+            (1, 1, 1, 0)
+        );
+        let (table, part_lists) = stats[2];
+        assert_eq!(table, "user_partlists");
+        assert_eq!((part_lists.rows, part_lists.removed), (0, 1));
+
+        let parts: u32 =
+            db.conn
+                .query_row("SELECT COUNT(*) FROM user_partlist_parts", [], |row| {
+                    row.get(0)
+                })?;
+        assert_eq!(parts, 0);
+        Ok(())
+    }
+}
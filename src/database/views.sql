@@ -0,0 +1,76 @@
+-- NOTE: Views cannot be parameterized, so they are all based on the latest
+-- version of each inventory. Other versions are selected through
+-- `inventories.version`.
+
+-- The latest version of the inventory of each set and minifigure.
+CREATE VIEW IF NOT EXISTS latest_inventories AS
+SELECT id, version, kind, set_num, fig_num
+FROM inventories
+WHERE is_latest;
+
+-- NOTE: Only the parts listed in the inventory of a set are included, not
+-- those of its sub-sets and minifigures.
+CREATE VIEW IF NOT EXISTS set_parts_flat AS
+SELECT
+    s.set_num,
+    s.name AS set_name,
+    ip.part_num,
+    p.name AS part_name,
+    pc.name AS category,
+    ip.color_id,
+    c.name AS color_name,
+    ip.quantity,
+    ip.is_spare
+FROM sets AS s
+JOIN latest_inventories AS li ON li.set_num = s.set_num
+JOIN inventory_parts AS ip ON ip.inventory_id = li.id
+JOIN parts AS p ON p.part_num = ip.part_num
+JOIN part_categories AS pc ON pc.id = p.part_cat_id
+JOIN colors AS c ON c.id = ip.color_id;
+
+CREATE VIEW IF NOT EXISTS minifig_parts AS
+SELECT
+    m.fig_num,
+    m.name AS fig_name,
+    ip.part_num,
+    p.name AS part_name,
+    pc.name AS category,
+    ip.color_id,
+    c.name AS color_name,
+    ip.quantity,
+    ip.is_spare
+FROM minifigs AS m
+JOIN latest_inventories AS li ON li.fig_num = m.fig_num
+JOIN inventory_parts AS ip ON ip.inventory_id = li.id
+JOIN parts AS p ON p.part_num = ip.part_num
+JOIN part_categories AS pc ON pc.id = p.part_cat_id
+JOIN colors AS c ON c.id = ip.color_id;
+
+CREATE VIEW IF NOT EXISTS set_minifigs AS
+SELECT
+    s.set_num,
+    s.name AS set_name,
+    m.fig_num,
+    m.name AS fig_name,
+    im.quantity
+FROM sets AS s
+JOIN latest_inventories AS li ON li.set_num = s.set_num
+JOIN inventory_minifigs AS im ON im.inventory_id = li.id
+JOIN minifigs AS m ON m.fig_num = im.fig_num;
+
+-- The colours each part appears in, with the number of sets and minifigures
+-- including it (spares excluded).
+CREATE VIEW IF NOT EXISTS part_colors AS
+SELECT
+    ip.part_num,
+    p.name AS part_name,
+    ip.color_id,
+    c.name AS color_name,
+    COUNT(DISTINCT li.set_num) AS num_sets,
+    COUNT(DISTINCT li.fig_num) AS num_minifigs
+FROM latest_inventories AS li
+JOIN inventory_parts AS ip ON ip.inventory_id = li.id
+JOIN parts AS p ON p.part_num = ip.part_num
+JOIN colors AS c ON c.id = ip.color_id
+WHERE NOT ip.is_spare
+GROUP BY ip.part_num, ip.color_id;
@@ -0,0 +1,16 @@
+//! Base directories of the XDG specification.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Returns the directory of this tool in an XDG base directory, given by the
+/// environment variable `var`, or else by `default` in the home directory.
+pub(crate) fn xdg_dir(var: &str, default: &str) -> Option<PathBuf> {
+    let base = env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(default)))?;
+    Some(base.join(clap::crate_name!()))
+}
@@ -0,0 +1,160 @@
+//! A content-addressed cache of images.
+//!
+//! Each image is stored once under its SHA-256 digest, fanned out by its first
+//! two hex digits and keeping the extension of its URL, e.g.
+//! `3f/3fa4...c1.jpg`.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+use url::Url;
+
+use crate::dirs;
+
+#[derive(Clone, Debug)]
+pub struct ImageCache {
+    dir: PathBuf,
+}
+
+/// An image stored in the cache.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct StoredImage {
+    /// The hex-encoded SHA-256 digest of the image.
+    pub sha256: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl ImageCache {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns `$XDG_CACHE_HOME/rbk-db/images`, or `~/.cache/rbk-db/images`.
+    pub fn default_dir() -> Option<PathBuf> {
+        Some(dirs::xdg_dir("XDG_CACHE_HOME", ".cache")?.join("images"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stores an image downloaded from a URL, unless an identical one is
+    /// already cached.
+    ///
+    /// The image is written to a temporary file of its own first, so the
+    /// cache never holds partial images, even when identical images are
+    /// stored concurrently.
+    pub fn store(&self, url: &Url, content: &[u8]) -> io::Result<StoredImage> {
+        let sha256 = Sha256::digest(content)
+            .iter()
+            .fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            });
+        let mut path = self.dir.join(&sha256[..2]).join(&sha256);
+        if let Some(extension) = extension(url) {
+            path.set_extension(extension);
+        }
+        if !path.exists() {
+            let parent = path.parent().expect("cached images are in a directory");
+            fs::create_dir_all(parent)?;
+            let mut tmp = NamedTempFile::new_in(parent)?;
+            tmp.write_all(content)?;
+            // Replacing an identical image stored in the meantime is harmless.
+            tmp.persist(&path)?;
+        }
+        Ok(StoredImage {
+            sha256,
+            path,
+            size: content.len() as u64,
+        })
+    }
+}
+
+/// Returns the extension of the last segment of a URL path, if it looks like
+/// one.
+fn extension(url: &Url) -> Option<&str> {
+    let segment = url.path_segments()?.next_back()?;
+    let (_, extension) = segment.rsplit_once('.')?;
+    (!extension.is_empty()
+        && extension.len() <= 5
+        && extension.chars().all(|c| c.is_ascii_alphanumeric()))
+    .then_some(extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Barrier, thread};
+
+    use tempfile::tempdir;
+    use url::Url;
+
+    use super::ImageCache;
+
+    #[test]
+    fn store() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let cache = ImageCache::new(dir.path());
+        let url = Url::parse("https://cdn.rebrickable.com/media/sets/1000-1.jpg")?;
+        let image = cache.store(&url, b"image")?;
+        assert_eq!(
+            image.sha256,
+            "6105d6cc76af400325e94d588ce511be5bfdbb73b437dc51eca43917d7a43e3d"
+        );
+        assert_eq!(
+            image.path,
+            dir.path().join("61").join(format!("{}.jpg", image.sha256))
+        );
+        assert_eq!(fs::read(&image.path)?, b"image");
+
+        // Identical images are stored once.
+        let other_url = Url::parse("https://cdn.rebrickable.com/media/sets/1000-2.jpg")?;
+        assert_eq!(cache.store(&other_url, b"image")?, image);
+        assert_eq!(fs::read_dir(dir.path().join("61"))?.count(), 1);
+
+        let no_extension = Url::parse("https://example.com/image?size=large")?;
+        let image = cache.store(&no_extension, b"other image")?;
+        assert_eq!(image.path.extension(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn store_concurrently() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let cache = ImageCache::new(dir.path());
+        let content = vec![0; 1 << 20];
+        let barrier = Barrier::new(8);
+        let images = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let (cache, content, barrier) = (&cache, &content, &barrier);
+                    scope.spawn(move || -> anyhow::Result<_> {
+                        let url =
+                            Url::parse(&format!("https://cdn.rebrickable.com/media/{i}.jpg"))?;
+                        barrier.wait();
+                        Ok(cache.store(&url, content)?)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?;
+        assert!(images.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(fs::read(&images[0].path)?, content);
+        let dir = images[0].path.parent().unwrap();
+        assert_eq!(fs::read_dir(dir)?.count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,438 @@
+//! Parts lists of LDraw models.
+//!
+//! Only type-1 lines (sub-file references) are relevant to compute a parts
+//! list: a reference either points to a submodel, which is expanded
+//! recursively, or to a part. Submodels are looked up first in the multi-part
+//! document (MPD) being read, then as `.ldr`/`.mpd` files next to it.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+/// The LDraw colour code inheriting the colour of the referencing line.
+pub const MAIN_COLOUR: u32 = 16;
+
+/// The lowest LDraw colour code that encodes an RGB value directly.
+const DIRECT_COLOUR_BASE: u32 = 0x0200_0000;
+
+/// Quantities of parts, by LDraw part id and colour code.
+type PartCounts = BTreeMap<(String, u32), u32>;
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Reference {
+    colour: u32,
+    name: String,
+}
+
+/// An LDraw model, made of one or more documents.
+#[derive(Debug)]
+pub struct Model {
+    main: String,
+    documents: HashMap<String, Vec<Reference>>,
+}
+
+impl Model {
+    /// Reads a model file, along with the submodel files it references.
+    pub fn open<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut model = Self {
+            main: String::new(),
+            documents: HashMap::new(),
+        };
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        model.main = model.load_file(path, &normalize_name(&file_name))?;
+
+        let mut pending = vec![model.main.clone()];
+        while let Some(name) = pending.pop() {
+            let references = model.documents[&name].clone();
+            for reference in references {
+                if model.documents.contains_key(&reference.name) {
+                    continue;
+                }
+                if let Some(path) = submodel_path(&dir, &reference.name) {
+                    let name = model.load_file(&path, &reference.name)?;
+                    pending.push(name);
+                }
+            }
+        }
+        Ok(model)
+    }
+
+    /// Reads a file referenced as `name`, returning the name of its first
+    /// document.
+    ///
+    /// When the first document is named otherwise, e.g. by a `0 FILE` line,
+    /// `name` is registered as an alias of it, so that the file is read once
+    /// and its references are not taken for parts.
+    fn load_file(&mut self, path: &Path, name: &str) -> anyhow::Result<String> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        let first = self
+            .add_documents(name, &content)
+            .map_err(|err| err.context(format!("invalid LDraw file {}", path.display())))?;
+        if first != name {
+            self.documents.entry(name.to_owned()).or_insert_with(|| {
+                vec![Reference {
+                    colour: MAIN_COLOUR,
+                    name: first.clone(),
+                }]
+            });
+        }
+        Ok(first)
+    }
+
+    /// Adds the documents of a file, returning the name of the first one.
+    fn add_documents(&mut self, file_name: &str, content: &str) -> anyhow::Result<String> {
+        let mut first = None;
+        let mut current = normalize_name(file_name);
+        let mut references = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("0") if tokens.next() == Some("FILE") => {
+                    let name = normalize_name(&tokens.collect::<Vec<_>>().join(" "));
+                    if first.is_some() || !references.is_empty() {
+                        self.documents
+                            .insert(current, std::mem::take(&mut references));
+                    }
+                    first.get_or_insert_with(|| name.clone());
+                    current = name;
+                }
+                Some("1") => {
+                    let reference = parse_reference(line)
+                        .ok_or_else(|| anyhow::anyhow!("invalid type-1 line {}", i + 1))?;
+                    references.push(reference);
+                }
+                _ => {}
+            }
+        }
+        let first = first.unwrap_or_else(|| current.clone());
+        self.documents.insert(current, references);
+        Ok(first)
+    }
+
+    /// Counts the parts of the model, by LDraw part id and colour code.
+    ///
+    /// Parts referenced with the main colour from the top-level document have
+    /// no actual colour and are reported with code 16.
+    pub fn parts(&self) -> anyhow::Result<PartCounts> {
+        let mut stack = Vec::new();
+        let mut counted = HashMap::new();
+        self.collect_parts(&self.main, MAIN_COLOUR, &mut stack, &mut counted)?;
+        Ok(counted
+            .remove(&(self.main.as_str(), MAIN_COLOUR))
+            .expect("main document is counted"))
+    }
+
+    /// Counts the parts of a document in a given colour, once for all of its
+    /// occurrences.
+    fn collect_parts<'a>(
+        &'a self,
+        name: &'a str,
+        colour: u32,
+        stack: &mut Vec<&'a str>,
+        counted: &mut HashMap<(&'a str, u32), PartCounts>,
+    ) -> anyhow::Result<()> {
+        if counted.contains_key(&(name, colour)) {
+            return Ok(());
+        }
+        if stack.contains(&name) {
+            anyhow::bail!("submodel {name} references itself");
+        }
+        stack.push(name);
+        let mut occurrences = BTreeMap::new();
+        for reference in &self.documents[name] {
+            let ref_colour = if reference.colour == MAIN_COLOUR {
+                colour
+            } else {
+                reference.colour
+            };
+            *occurrences
+                .entry((reference.name.as_str(), ref_colour))
+                .or_insert(0u32) += 1;
+        }
+        let mut parts = BTreeMap::new();
+        for ((ref_name, ref_colour), occurrences) in occurrences {
+            if self.documents.contains_key(ref_name) {
+                self.collect_parts(ref_name, ref_colour, stack, counted)?;
+                for (part, quantity) in &counted[&(ref_name, ref_colour)] {
+                    let total: &mut u32 = parts.entry(part.clone()).or_default();
+                    *total = quantity
+                        .checked_mul(occurrences)
+                        .and_then(|quantity| total.checked_add(quantity))
+                        .ok_or_else(|| anyhow::anyhow!("model has too many parts"))?;
+                }
+            } else {
+                let part_id = ref_name.strip_suffix(".dat").unwrap_or(ref_name).to_owned();
+                *parts.entry((part_id, ref_colour)).or_default() += occurrences;
+            }
+        }
+        stack.pop();
+        counted.insert((name, colour), parts);
+        Ok(())
+    }
+}
+
+fn parse_reference(line: &str) -> Option<Reference> {
+    // A type-1 line is `1 <colour> x y z a b c d e f g h i <file>`, where the
+    // file name may contain spaces.
+    let mut rest = line.trim_start();
+    let mut tokens = Vec::with_capacity(14);
+    for _ in 0..14 {
+        let end = rest.find(char::is_whitespace)?;
+        tokens.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    let name = rest.trim_end();
+    if name.is_empty() {
+        return None;
+    }
+    let colour = tokens[1].parse().ok()?;
+    if tokens[2..]
+        .iter()
+        .any(|token| token.parse::<f64>().is_err())
+    {
+        return None;
+    }
+    Some(Reference {
+        colour,
+        name: normalize_name(name),
+    })
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().replace('\\', "/").to_lowercase()
+}
+
+fn submodel_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let path = dir.join(name);
+    let is_model = path
+        .extension()
+        .is_some_and(|ext| ext == "ldr" || ext == "mpd");
+    if !is_model {
+        return None;
+    }
+    // LDraw names are case-insensitive, unlike most filesystems.
+    if path.is_file() {
+        return Some(path);
+    }
+    let parent = path.parent()?;
+    let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+    fs::read_dir(parent)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().to_lowercase() == file_name)
+        })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MappingKind {
+    Part,
+    Color,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MappingRow {
+    kind: MappingKind,
+    ldraw_id: String,
+    rebrickable_id: String,
+}
+
+/// A mapping from LDraw part ids and colour codes to Rebrickable ones.
+///
+/// Identifiers that are not mapped explicitly are assumed to be the same in
+/// both catalogues, which holds for most parts and for the classic colours.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Mapping {
+    parts: HashMap<String, String>,
+    colors: HashMap<u32, i32>,
+}
+
+impl Mapping {
+    /// Reads a mapping from a CSV file with columns `kind` (`part` or
+    /// `color`), `ldraw_id` and `rebrickable_id`.
+    pub fn read_csv<R>(rdr: R) -> anyhow::Result<Self>
+    where
+        R: Read,
+    {
+        let mut mapping = Self::default();
+        for row in csv::Reader::from_reader(rdr).into_deserialize() {
+            let row: MappingRow = row?;
+            match row.kind {
+                MappingKind::Part => {
+                    mapping
+                        .parts
+                        .insert(normalize_name(&row.ldraw_id), row.rebrickable_id);
+                }
+                MappingKind::Color => {
+                    mapping
+                        .colors
+                        .insert(row.ldraw_id.parse()?, row.rebrickable_id.parse()?);
+                }
+            }
+        }
+        Ok(mapping)
+    }
+
+    pub fn part_num<'a>(&'a self, ldraw_id: &'a str) -> &'a str {
+        self.parts.get(ldraw_id).map_or(ldraw_id, String::as_str)
+    }
+
+    pub fn color_id(&self, ldraw_code: u32) -> Option<i32> {
+        match self.colors.get(&ldraw_code) {
+            Some(&color_id) => Some(color_id),
+            // Direct colours, such as `0x2RRGGBB`, have no catalogue entry.
+            None if ldraw_code >= DIRECT_COLOUR_BASE => None,
+            None => i32::try_from(ldraw_code).ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use tempfile::tempdir;
+
+    use super::{Mapping, Model};
+
+    fn fixture_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/ldraw/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn mpd_parts() -> anyhow::Result<()> {
+        let model = Model::open(fixture_path("fire-cart.mpd"))?;
+        let parts = model.parts()?;
+        let parts: Vec<_> = parts
+            .iter()
+            .map(|((part, colour), quantity)| (part.as_str(), *colour, *quantity))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("3001", 4, 2),
+                ("3003", 15, 3),
+                ("3626c", 14, 2),
+                ("973pr0001", 1, 1),
+                ("973pr0001", 4, 1),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn subdirectory_submodel() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("sub"))?;
+        fs::write(
+            dir.path().join("sub/door.ldr"),
+            "1 4 0 0 0 1 0 0 0 1 0 0 0 1 3001.dat\n\
+             1 16 0 0 0 1 0 0 0 1 0 0 0 1 3003.dat\n",
+        )?;
+        let path = dir.path().join("house.ldr");
+        fs::write(
+            &path,
+            "1 15 0 0 0 1 0 0 0 1 0 0 0 1 sub/door.ldr\n\
+             1 15 0 0 0 1 0 0 0 1 0 0 0 1 sub\\door.ldr\n\
+             1 1 0 0 0 1 0 0 0 1 0 0 0 1 sub/door.ldr\n",
+        )?;
+        let model = Model::open(&path)?;
+        let parts: Vec<_> = model
+            .parts()?
+            .into_iter()
+            .map(|((part, colour), quantity)| (part, colour, quantity))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("3001".to_owned(), 4, 3),
+                ("3003".to_owned(), 1, 1),
+                ("3003".to_owned(), 15, 2),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nested_submodels() -> anyhow::Result<()> {
+        // Each submodel holds two copies of the next one, so expanding every
+        // occurrence would take 2^30 steps.
+        let mut content = String::new();
+        for i in 0..30 {
+            content.push_str(&format!("0 FILE m{i}.ldr\n"));
+            for _ in 0..2 {
+                let next = i + 1;
+                content.push_str(&format!("1 16 0 0 0 1 0 0 0 1 0 0 0 1 m{next}.ldr\n"));
+            }
+        }
+        content.push_str("0 FILE m30.ldr\n1 4 0 0 0 1 0 0 0 1 0 0 0 1 3001.dat\n");
+        let dir = tempdir()?;
+        let path = dir.path().join("tower.mpd");
+        fs::write(&path, content)?;
+        let model = Model::open(&path)?;
+        assert_eq!(model.parts()?[&("3001".to_owned(), 4)], 1 << 30);
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_submodel() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("loop.mpd");
+        fs::write(
+            &path,
+            "0 FILE a.ldr\n\
+             1 16 0 0 0 1 0 0 0 1 0 0 0 1 b.ldr\n\
+             0 FILE b.ldr\n\
+             1 16 0 0 0 1 0 0 0 1 0 0 0 1 a.ldr\n",
+        )?;
+        let model = Model::open(&path)?;
+        assert!(model.parts().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_line() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("bad.ldr");
+        fs::write(&path, "1 4 0 0 0 1 0 0 0 1 0 0 0 1\n")?;
+        assert!(Model::open(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn mapping() -> anyhow::Result<()> {
+        let csv = "kind,ldraw_id,rebrickable_id\n\
+                   part,3626cp01,3626cpr0001\n\
+                   color,256,0\n";
+        let mapping = Mapping::read_csv(csv.as_bytes())?;
+        assert_eq!(mapping.part_num("3626cp01"), "3626cpr0001");
+        assert_eq!(mapping.part_num("3001"), "3001");
+        assert_eq!(mapping.color_id(256), Some(0));
+        assert_eq!(mapping.color_id(4), Some(4));
+        assert_eq!(mapping.color_id(0x0200_00ff), None);
+        Ok(())
+    }
+}
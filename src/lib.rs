@@ -0,0 +1,101 @@
+//! Import the Rebrickable LEGO database into SQLite.
+
+mod commands;
+mod config;
+mod database;
+mod dirs;
+mod images;
+mod ldraw;
+mod package;
+mod part_graph;
+mod progress;
+mod rebrickable;
+mod shell;
+mod site;
+mod types;
+
+#[cfg(test)]
+mod test_server;
+
+/// The items used by the benchmarks, which are not a stable interface.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::{
+        database::{Database, InsertMode},
+        rebrickable::record::{Color, Element, Part, PartCategory},
+        types::{PartMaterial, Rgb},
+    };
+}
+
+use std::path::PathBuf;
+
+use clap::{CommandFactory, FromArgMatches};
+
+pub use self::commands::{Command, complete_dynamically, run};
+use self::config::Config;
+
+#[derive(Debug, clap::Parser)]
+#[command(about)]
+pub struct Args {
+    /// The configuration file providing defaults for the arguments.
+    /// Defaults to `$XDG_CONFIG_HOME/rbk-db/config.toml`, if it exists.
+    #[arg(global = true, long, env = config::CONFIG_ENV)]
+    pub config: Option<PathBuf>,
+    /// Set the verbosity level for log messages.
+    #[arg(global = true, long, default_value = "info", env = "RBK_DB_LOG_LEVEL")]
+    pub log_level: tracing::level_filters::LevelFilter,
+    /// Set the format of log messages.
+    #[arg(
+        global = true,
+        long,
+        value_enum,
+        default_value_t,
+        env = "RBK_DB_LOG_FORMAT"
+    )]
+    pub log_format: LogFormat,
+    /// The command to execute.
+    #[command(subcommand)]
+    pub command: Command,
+    /// The invalid settings of the configuration file that the command does
+    /// not use, to report once logging is set up.
+    #[arg(skip)]
+    pub config_warnings: Vec<String>,
+}
+
+impl Args {
+    /// Parses the command-line arguments, with defaults from the
+    /// configuration file.
+    pub fn parse_with_config() -> anyhow::Result<Self> {
+        let config = Config::load()?;
+        let (cmd, invalid) = config.apply_valid(Self::command());
+        let matches = cmd.get_matches();
+        let config_warnings = invalid.check(&matches)?;
+        Ok(Self {
+            config_warnings,
+            ..Self::from_arg_matches(&matches)?
+        })
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+pub fn setup_logging(
+    log_level: tracing::level_filters::LevelFilter,
+    log_format: LogFormat,
+) -> anyhow::Result<()> {
+    let builder = tracing_subscriber::fmt()
+        .with_writer(|| progress::StderrWriter)
+        .with_max_level(log_level);
+    match log_format {
+        LogFormat::Text => tracing::subscriber::set_global_default(builder.finish())?,
+        LogFormat::Json => tracing::subscriber::set_global_default(builder.json().finish())?,
+    }
+    Ok(())
+}
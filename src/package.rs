@@ -0,0 +1,253 @@
+//! Packages of finished databases, to distribute them without hitting the
+//! Rebrickable CDN from every machine.
+//!
+//! A package is a gzipped tarball holding a JSON manifest, then the database
+//! itself. The manifest comes first so the database can be verified while it
+//! is being extracted, even from a stream.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::database::{self, Database};
+
+const MANIFEST_NAME: &str = "manifest.json";
+const DATABASE_NAME: &str = "rebrickable.db";
+/// The version of the package format.
+const VERSION: u32 = 1;
+
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// The size of the database, in bytes.
+    pub size: u64,
+    /// The hex-encoded SHA-256 digest of the database.
+    pub sha256: String,
+    /// The content of the `metadata` table of the database.
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Writes a package of a database.
+pub fn create(database: &Path, package: &Path) -> anyhow::Result<Manifest> {
+    let metadata = Database::open_read_only(database)?.metadata()?;
+    let mut hasher = HashingWriter::new(io::sink());
+    let size = io::copy(&mut File::open(database)?, &mut hasher)?;
+    let manifest = Manifest {
+        version: VERSION,
+        size,
+        sha256: hasher.hex_digest(),
+        metadata,
+    };
+
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        File::create(package)?,
+        Compression::default(),
+    ));
+    let json = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_NAME, json.as_slice())?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, DATABASE_NAME, File::open(database)?)?;
+    builder.into_inner()?.finish()?.sync_all()?;
+    Ok(manifest)
+}
+
+/// Extracts the database of a package to `database`, after checking it
+/// against the manifest.
+///
+/// The database is extracted next to its destination and only renamed once
+/// verified, so an existing database is left untouched on failure.
+/// An existing database must not be in use in WAL mode.
+pub fn unpack<R>(package: R, database: &Path) -> anyhow::Result<Manifest>
+where
+    R: Read,
+{
+    database::ensure_replaceable(database)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(package));
+    let mut entries = archive.entries()?;
+
+    let mut entry = entries
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("package is empty"))??;
+    if entry.path()?.as_os_str() != MANIFEST_NAME {
+        anyhow::bail!("package does not start with a manifest");
+    }
+    let manifest: Manifest = serde_json::from_reader(&mut entry)?;
+    if manifest.version != VERSION {
+        anyhow::bail!("unsupported package version {}", manifest.version);
+    }
+
+    let mut entry = entries
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("package has no database"))??;
+    if entry.path()?.as_os_str() != DATABASE_NAME {
+        anyhow::bail!("unexpected entry {} in package", entry.path()?.display());
+    }
+    let mut tmp_path = PathBuf::from(database).into_os_string();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    let mut writer = HashingWriter::new(File::create(&tmp_path)?);
+    let res = io::copy(&mut entry, &mut writer)
+        .map_err(anyhow::Error::from)
+        .and_then(|size| {
+            let sha256 = writer.hex_digest();
+            if size != manifest.size || sha256 != manifest.sha256 {
+                anyhow::bail!(
+                    "database does not match the manifest: expected {} bytes with SHA-256 {}, got {size} bytes with SHA-256 {sha256}",
+                    manifest.size,
+                    manifest.sha256,
+                );
+            }
+            writer.inner.sync_all()?;
+            Ok(())
+        });
+    if let Err(err) = res {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err);
+    }
+    fs::rename(&tmp_path, database)?;
+    Ok(manifest)
+}
+
+/// A writer computing the SHA-256 digest of what goes through it.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn hex_digest(&self) -> String {
+        self.hasher
+            .clone()
+            .finalize()
+            .iter()
+            .fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+}
+
+impl<W> Write for HashingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Read, Write},
+    };
+
+    use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+    use rusqlite::Connection;
+    use tempfile::tempdir;
+
+    use crate::database::{Database, FinalizeOptions, JournalMode};
+
+    #[test]
+    fn create_and_unpack() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        let db = Database::open(&database)?;
+        db.set_metadata("tables", "colors")?;
+        db.finalize(FinalizeOptions::default())?;
+        let package = dir.path().join("rebrickable.tar.gz");
+        let manifest = super::create(&database, &package)?;
+        assert_eq!(manifest.metadata["tables"], "colors");
+        assert_eq!(manifest.size, fs::metadata(&database)?.len());
+
+        let unpacked = dir.path().join("unpacked.db");
+        let unpacked_manifest = super::unpack(fs::File::open(&package)?, &unpacked)?;
+        assert_eq!(unpacked_manifest, manifest);
+        assert_eq!(fs::read(&unpacked)?, fs::read(&database)?);
+        Ok(())
+    }
+
+    #[test]
+    fn unpack_in_use() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        Database::open(&database)?.finalize(FinalizeOptions {
+            journal_mode: JournalMode::Wal,
+            page_size: None,
+        })?;
+        let package = dir.path().join("rebrickable.tar.gz");
+        super::create(&database, &package)?;
+
+        let unpacked = dir.path().join("unpacked.db");
+        super::unpack(fs::File::open(&package)?, &unpacked)?;
+        let reader = Connection::open(&unpacked)?;
+        reader.query_row("SELECT COUNT(*) FROM metadata", [], |_| Ok(()))?;
+        let err = super::unpack(fs::File::open(&package)?, &unpacked).unwrap_err();
+        assert!(err.to_string().contains("in use"), "{err}");
+
+        drop(reader);
+        super::unpack(fs::File::open(&package)?, &unpacked)?;
+        Ok(())
+    }
+
+    #[test]
+    fn unpack_corrupted() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let database = dir.path().join("rebrickable.db");
+        Database::open(&database)?.finalize(FinalizeOptions::default())?;
+        let package = dir.path().join("rebrickable.tar.gz");
+        super::create(&database, &package)?;
+
+        // Flip a byte of the database, keeping the archive itself valid.
+        let mut tarball = Vec::new();
+        GzDecoder::new(fs::File::open(&package)?).read_to_end(&mut tarball)?;
+        // The archive ends with two empty blocks.
+        let offset = tarball[..tarball.len() - 1024]
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .expect("database is not empty");
+        tarball[offset] ^= 0xff;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tarball)?;
+        let corrupted = encoder.finish()?;
+
+        let unpacked = dir.path().join("unpacked.db");
+        let err = super::unpack(corrupted.as_slice(), &unpacked).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("database does not match the manifest")
+        );
+        assert!(!unpacked.exists());
+        assert!(!dir.path().join("unpacked.db.tmp").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+//! Relationships between parts, as a graph.
+//!
+//! Relationships are directed from a child part to a parent part: a printed
+//! part is the child of its unprinted base, and a sub-part is the child of the
+//! assembly it belongs to. Equivalence classes, on the other hand, ignore the
+//! direction of the relationships.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{rebrickable::record::PartRelationship, types::PartRelationType};
+
+/// The relationship types that make parts interchangeable by default.
+pub const DEFAULT_EQUIVALENCE_TYPES: &[PartRelationType] =
+    &[PartRelationType::Mold, PartRelationType::Alternate];
+
+#[derive(Clone, Debug, Default)]
+pub struct PartGraph {
+    /// The parents of each part, by relationship type.
+    parents: HashMap<String, Vec<(PartRelationType, String)>>,
+    /// The children of each part, by relationship type.
+    children: HashMap<String, Vec<(PartRelationType, String)>>,
+}
+
+impl PartGraph {
+    pub fn new<I>(relationships: I) -> Self
+    where
+        I: IntoIterator<Item = PartRelationship>,
+    {
+        let mut graph = Self::default();
+        for rel in relationships {
+            graph
+                .parents
+                .entry(rel.child_part_num.clone())
+                .or_default()
+                .push((rel.rel_type, rel.parent_part_num.clone()));
+            graph
+                .children
+                .entry(rel.parent_part_num)
+                .or_default()
+                .push((rel.rel_type, rel.child_part_num));
+        }
+        graph
+    }
+
+    fn neighbors<'a>(
+        &'a self,
+        part_num: &str,
+        rel_types: &'a [PartRelationType],
+    ) -> impl Iterator<Item = &'a str> {
+        let parents = self.parents.get(part_num).into_iter().flatten();
+        let children = self.children.get(part_num).into_iter().flatten();
+        parents
+            .chain(children)
+            .filter(|(rel_type, _)| rel_types.contains(rel_type))
+            .map(|(_, part_num)| part_num.as_str())
+    }
+
+    /// Returns the parts that are transitively related to the given part
+    /// through relationships of the given types, including the part itself.
+    pub fn equivalents(&self, part_num: &str, rel_types: &[PartRelationType]) -> BTreeSet<String> {
+        let mut visited = BTreeSet::from([part_num.to_owned()]);
+        let mut pending = vec![part_num.to_owned()];
+        while let Some(part_num) = pending.pop() {
+            for neighbor in self.neighbors(&part_num, rel_types) {
+                if visited.insert(neighbor.to_owned()) {
+                    pending.push(neighbor.to_owned());
+                }
+            }
+        }
+        visited
+    }
+
+    /// Partitions the parts with relationships of the given types into
+    /// equivalence classes, each one named after its smallest part number.
+    ///
+    /// Parts without any such relationship are not included.
+    pub fn equivalence_classes(
+        &self,
+        rel_types: &[PartRelationType],
+    ) -> BTreeMap<String, BTreeSet<String>> {
+        let mut classes = BTreeMap::new();
+        let mut classified = BTreeSet::new();
+        let mut part_nums: Vec<&str> = self
+            .parents
+            .keys()
+            .chain(self.children.keys())
+            .map(String::as_str)
+            .collect();
+        part_nums.sort_unstable();
+        for part_num in part_nums {
+            if classified.contains(part_num) || self.neighbors(part_num, rel_types).next().is_none()
+            {
+                continue;
+            }
+            let class = self.equivalents(part_num, rel_types);
+            classified.extend(class.iter().cloned());
+            let name = class.first().expect("class contains the part").clone();
+            classes.insert(name, class);
+        }
+        classes
+    }
+
+    /// Returns the unprinted base of a printed or patterned part, following
+    /// print and pattern relationships transitively.
+    pub fn unprinted_base(&self, part_num: &str) -> Option<&str> {
+        let mut base = None;
+        let mut current = part_num;
+        let mut visited = BTreeSet::from([part_num]);
+        while let Some(parent) = self
+            .parents
+            .get(current)
+            .into_iter()
+            .flatten()
+            .filter(|(rel_type, _)| {
+                matches!(
+                    rel_type,
+                    PartRelationType::Print | PartRelationType::Pattern
+                )
+            })
+            .map(|(_, parent)| parent.as_str())
+            .min()
+        {
+            if !visited.insert(parent) {
+                break;
+            }
+            base = Some(parent);
+            current = parent;
+        }
+        base
+    }
+
+    /// Returns the sub-parts of an assembly, recursively.
+    pub fn subparts(&self, part_num: &str) -> BTreeSet<String> {
+        let mut subparts = BTreeSet::new();
+        let mut pending = vec![part_num];
+        while let Some(part_num) = pending.pop() {
+            for (rel_type, child) in self.children.get(part_num).into_iter().flatten() {
+                if *rel_type == PartRelationType::SubPart && subparts.insert(child.clone()) {
+                    pending.push(child);
+                }
+            }
+        }
+        subparts.remove(part_num);
+        subparts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{DEFAULT_EQUIVALENCE_TYPES, PartGraph};
+    use crate::{rebrickable::record::PartRelationship, types::PartRelationType};
+
+    fn graph() -> PartGraph {
+        let rel = |rel_type, child: &str, parent: &str| PartRelationship {
+            rel_type,
+            child_part_num: child.to_owned(),
+            parent_part_num: parent.to_owned(),
+        };
+        PartGraph::new([
+            rel(PartRelationType::Mold, "3001a", "3001"),
+            rel(PartRelationType::Alternate, "3001b", "3001a"),
+            rel(PartRelationType::Print, "3626cpr0001", "3626c"),
+            rel(PartRelationType::Pattern, "3626cpr0001pat", "3626cpr0001"),
+            rel(PartRelationType::SubPart, "970", "73200"),
+            rel(PartRelationType::SubPart, "971", "73200"),
+            rel(PartRelationType::SubPart, "971a", "971"),
+        ])
+    }
+
+    fn set(part_nums: &[&str]) -> BTreeSet<String> {
+        part_nums.iter().map(|&p| p.to_owned()).collect()
+    }
+
+    #[test]
+    fn equivalents() {
+        let graph = graph();
+        assert_eq!(
+            graph.equivalents("3001b", DEFAULT_EQUIVALENCE_TYPES),
+            set(&["3001", "3001a", "3001b"])
+        );
+        assert_eq!(
+            graph.equivalents("3001b", &[PartRelationType::Mold]),
+            set(&["3001b"])
+        );
+        assert_eq!(
+            graph.equivalents("3626c", DEFAULT_EQUIVALENCE_TYPES),
+            set(&["3626c"])
+        );
+    }
+
+    #[test]
+    fn equivalence_classes() {
+        let classes = graph().equivalence_classes(DEFAULT_EQUIVALENCE_TYPES);
+        assert_eq!(classes.len(), 1);
+        assert_eq!(classes["3001"], set(&["3001", "3001a", "3001b"]));
+    }
+
+    #[test]
+    fn unprinted_base() {
+        let graph = graph();
+        assert_eq!(graph.unprinted_base("3626cpr0001pat"), Some("3626c"));
+        assert_eq!(graph.unprinted_base("3626c"), None);
+    }
+
+    #[test]
+    fn subparts() {
+        let graph = graph();
+        assert_eq!(graph.subparts("73200"), set(&["970", "971", "971a"]));
+        assert!(graph.subparts("3001").is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+//! Progress of long-running tasks.
+//!
+//! Progress is shown as bars when stderr is a terminal, and as periodic log
+//! lines otherwise.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// The interval between log lines when progress bars are disabled.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+static GLOBAL: LazyLock<Progress> = LazyLock::new(|| {
+    if io::stderr().is_terminal() {
+        Progress::with_bars()
+    } else {
+        Progress::with_logs()
+    }
+});
+
+#[derive(Clone, Debug)]
+pub struct Progress {
+    bars: Option<MultiProgress>,
+}
+
+impl Progress {
+    /// Returns the progress shared by all tasks, with bars drawn on stderr
+    /// if it is a terminal.
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    fn with_bars() -> Self {
+        Self {
+            bars: Some(MultiProgress::new()),
+        }
+    }
+
+    fn with_logs() -> Self {
+        Self { bars: None }
+    }
+
+    /// Tracks the bytes downloaded for a table, out of `total` if known.
+    pub fn download(&self, table: &'static str, total: Option<u64>) -> ProgressHandle {
+        let style = if total.is_some() {
+            "{prefix:>20} {wide_bar} {binary_bytes}/{binary_total_bytes} ({binary_bytes_per_sec})"
+        } else {
+            "{prefix:>20} {spinner} {binary_bytes} ({binary_bytes_per_sec})"
+        };
+        self.handle(table, "download", "bytes", total, style)
+    }
+
+    /// Tracks the rows inserted into a table.
+    pub fn insert(&self, table: &'static str) -> ProgressHandle {
+        self.handle(
+            table,
+            "insert",
+            "rows",
+            None,
+            "{prefix:>20} {spinner} {human_pos} rows ({per_sec})",
+        )
+    }
+
+    fn handle(
+        &self,
+        table: &'static str,
+        task: &'static str,
+        unit: &'static str,
+        total: Option<u64>,
+        template: &str,
+    ) -> ProgressHandle {
+        let bar = self.bars.as_ref().map(|bars| {
+            let bar = ProgressBar::with_draw_target(total, indicatif::ProgressDrawTarget::hidden())
+                .with_style(ProgressStyle::with_template(template).expect("template is valid"))
+                .with_prefix(format!("{task} {table}"));
+            bars.add(bar)
+        });
+        ProgressHandle {
+            bar,
+            table,
+            task,
+            unit,
+            position: 0,
+            total,
+            start: Instant::now(),
+            last_log: Instant::now(),
+        }
+    }
+
+    /// Runs a function with the progress bars hidden, so it can write to
+    /// stderr.
+    fn suspend<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        match &self.bars {
+            Some(bars) => bars.suspend(f),
+            None => f(),
+        }
+    }
+}
+
+/// The progress of a single task.
+#[derive(Debug)]
+pub struct ProgressHandle {
+    bar: Option<ProgressBar>,
+    table: &'static str,
+    task: &'static str,
+    unit: &'static str,
+    position: u64,
+    total: Option<u64>,
+    start: Instant,
+    last_log: Instant,
+}
+
+impl ProgressHandle {
+    pub fn inc(&mut self, delta: u64) {
+        self.position += delta;
+        match &self.bar {
+            Some(bar) => bar.inc(delta),
+            None => {
+                if self.last_log.elapsed() >= LOG_INTERVAL {
+                    self.last_log = Instant::now();
+                    self.log();
+                }
+            }
+        }
+    }
+
+    fn log(&self) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        tracing::info!(
+            table = self.table,
+            task = self.task,
+            unit = self.unit,
+            position = self.position,
+            total = self.total,
+            rate = (self.position as f64 / elapsed).round(),
+            "{} {}: {} {}",
+            self.task,
+            self.table,
+            self.position,
+            self.unit,
+        );
+    }
+}
+
+impl Drop for ProgressHandle {
+    fn drop(&mut self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// A writer to stderr that does not garble the progress bars.
+#[derive(Debug)]
+pub struct StderrWriter;
+
+impl Write for StderrWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Progress::global().suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+        time::Instant,
+    };
+
+    use super::{LOG_INTERVAL, Progress};
+
+    /// A writer collecting log lines in memory.
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Logs {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8_lossy(&self.0.lock().unwrap())
+                .lines()
+                .map(str::to_owned)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn logs() {
+        let logs = Logs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let mut handle = Progress::with_logs().download("colors", Some(100));
+            assert!(handle.bar.is_none());
+
+            // Nothing is logged until the interval has elapsed.
+            handle.inc(10);
+            assert!(logs.lines().is_empty());
+
+            handle.last_log = Instant::now() - LOG_INTERVAL;
+            handle.inc(20);
+            handle.inc(30);
+            let lines = logs.lines();
+            assert_eq!(lines.len(), 1);
+            assert!(lines[0].contains("download colors: 30 bytes"), "{lines:?}");
+            assert!(lines[0].contains("total=100"), "{lines:?}");
+            assert_eq!(handle.position, 60);
+        });
+    }
+
+    #[test]
+    fn bars() {
+        let progress = Progress::with_bars();
+        let mut handle = progress.insert("parts");
+        handle.inc(5);
+        handle.inc(3);
+        assert_eq!(handle.position, 8);
+        let bar = handle.bar.clone().unwrap();
+        assert_eq!(bar.position(), 8);
+        assert_eq!(bar.length(), None);
+
+        let handle = progress.download("parts", Some(1024));
+        let download = handle.bar.clone().unwrap();
+        assert_eq!(download.length(), Some(1024));
+
+        // Bars are cleared once their task is done.
+        assert!(!download.is_finished());
+        drop(handle);
+        assert!(download.is_finished());
+    }
+}
@@ -1,5 +1,15 @@
+pub mod api;
 mod client;
+pub mod list;
 pub mod record;
+pub mod stream;
 pub mod table;
 
-pub use self::{client::Client, table::Table};
+pub use self::{
+    client::{
+        CHUNKS_CAPACITY, Client, DEFAULT_CONNECT_TIMEOUT, DEFAULT_DOWNLOADS_URL,
+        DEFAULT_MAX_CONCURRENCY, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_DELAY, DEFAULT_TIMEOUT,
+        DownloadHandler, DownloadStats,
+    },
+    table::Table,
+};
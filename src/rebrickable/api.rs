@@ -0,0 +1,301 @@
+//! A client for the Rebrickable API v3, for data that the CSV dumps lack.
+
+pub mod users;
+
+use std::{collections::BTreeMap, time::Duration};
+
+use reqwest::{StatusCode, header};
+use serde::{Deserialize, de::DeserializeOwned};
+use url::Url;
+
+use super::{Client, client::retry_after};
+
+pub const DEFAULT_BASE_URL: &str = "https://rebrickable.com/api/v3/";
+const PAGE_SIZE: &str = "1000";
+const MAX_RETRIES: u32 = 5;
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// An API-key authenticated client.
+#[derive(Clone, Debug)]
+pub struct Api {
+    client: Client,
+    api_key: String,
+    base_url: Url,
+}
+
+impl Api {
+    pub(super) fn new(client: Client, api_key: String) -> Self {
+        Self {
+            client,
+            api_key,
+            base_url: Url::parse(DEFAULT_BASE_URL).expect("default base URL is valid"),
+        }
+    }
+
+    /// Sets the URL the API endpoints are relative to.
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Sends a GET request, waiting and retrying as long as the server
+    /// responds that the rate limit is exceeded.
+    async fn get(&self, url: Url) -> anyhow::Result<reqwest::Response> {
+        let mut retries = 0;
+        loop {
+            let response = self
+                .client
+                .reqwest
+                .get(url.clone())
+                .header(header::AUTHORIZATION, format!("key {}", self.api_key))
+                .header(header::ACCEPT, "application/json")
+                .send()
+                .await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS || retries >= MAX_RETRIES {
+                return Ok(response.error_for_status()?);
+            }
+            let delay = retry_after(&response).unwrap_or(DEFAULT_RETRY_AFTER);
+            tracing::debug!("rate limit exceeded, retrying in {}s", delay.as_secs_f64());
+            tokio::time::sleep(delay).await;
+            retries += 1;
+        }
+    }
+
+    /// Fetches all the results of a paginated endpoint.
+    async fn get_all<T>(&self, path: &str, query: &[(&str, &str)]) -> anyhow::Result<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut url = self.base_url.join(path)?;
+        url.query_pairs_mut()
+            .extend_pairs(query)
+            .append_pair("page_size", PAGE_SIZE);
+
+        let mut results = Vec::new();
+        let mut next = Some(url);
+        while let Some(url) = next {
+            tracing::debug!("fetching {url}");
+            let page: Page<T> = self.get(url).await?.json().await?;
+            results.extend(page.results);
+            next = page.next;
+        }
+        Ok(results)
+    }
+
+    pub async fn colors(&self) -> anyhow::Result<Vec<ApiColor>> {
+        self.get_all("lego/colors/", &[]).await
+    }
+
+    pub async fn parts(&self) -> anyhow::Result<Vec<ApiPart>> {
+        self.get_all("lego/parts/", &[("inc_part_details", "1")])
+            .await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    next: Option<Url>,
+    results: Vec<T>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiColor {
+    pub id: i32,
+    #[serde(default)]
+    pub external_ids: BTreeMap<String, ApiColorExternalIds>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiColorExternalIds {
+    pub ext_ids: Vec<Option<i64>>,
+    pub ext_descrs: Vec<Vec<String>>,
+}
+
+impl ApiColor {
+    pub fn external_ids(&self) -> impl Iterator<Item = ColorExternalId> + '_ {
+        self.external_ids.iter().flat_map(move |(source, ids)| {
+            ids.ext_ids
+                .iter()
+                .zip(
+                    ids.ext_descrs
+                        .iter()
+                        .map(Some)
+                        .chain(std::iter::repeat(None)),
+                )
+                .filter_map(move |(external_id, descrs)| {
+                    Some(ColorExternalId {
+                        color_id: self.id,
+                        source: source.clone(),
+                        external_id: (*external_id)?,
+                        name: descrs.and_then(|descrs| descrs.first()).cloned(),
+                    })
+                })
+        })
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiPart {
+    pub part_num: String,
+    #[serde(default)]
+    pub external_ids: BTreeMap<String, Vec<String>>,
+}
+
+impl ApiPart {
+    pub fn external_ids(&self) -> impl Iterator<Item = PartExternalId> + '_ {
+        self.external_ids.iter().flat_map(move |(source, ids)| {
+            ids.iter().map(move |external_id| PartExternalId {
+                part_num: self.part_num.clone(),
+                source: source.clone(),
+                external_id: external_id.clone(),
+            })
+        })
+    }
+}
+
+/// The identifier of a colour in another catalogue.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ColorExternalId {
+    pub color_id: i32,
+    pub source: String,
+    pub external_id: i64,
+    pub name: Option<String>,
+}
+
+/// The identifier of a part in another catalogue.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PartExternalId {
+    pub part_num: String,
+    pub source: String,
+    pub external_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorExternalId, PartExternalId};
+    use crate::{
+        rebrickable::Client,
+        test_server::{TestResponse, TestServer},
+    };
+
+    #[tokio::test]
+    async fn colors() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route(
+            "/lego/colors/?page_size=1000",
+            TestResponse::json(format!(
+                r#"{{
+                    "count": 2,
+                    "next": "{}",
+                    "previous": null,
+                    "results": [{{
+                        "id": 0,
+                        "name": "Black",
+                        "external_ids": {{
+                            "LDraw": {{"ext_ids": [0], "ext_descrs": [["Black"]]}},
+                            "Peeron": {{"ext_ids": [null], "ext_descrs": [["black"]]}}
+                        }}
+                    }}]
+                }}"#,
+                server.url("/lego/colors/?page=2&page_size=1000"),
+            )),
+        );
+        server.route(
+            "/lego/colors/?page=2&page_size=1000",
+            TestResponse::new(429).with_header("Retry-After", "0"),
+        );
+        server.route(
+            "/lego/colors/?page=2&page_size=1000",
+            TestResponse::json(
+                r#"{
+                    "count": 2,
+                    "next": null,
+                    "previous": null,
+                    "results": [{"id": 9999, "name": "[No Color/Any Color]", "external_ids": {}}]
+                }"#,
+            ),
+        );
+
+        let api = Client::new().api("secret").with_base_url(server.url("/"));
+        let colors = api.colors().await?;
+        assert_eq!(colors.len(), 2);
+        let external_ids: Vec<_> = colors.iter().flat_map(|c| c.external_ids()).collect();
+        assert_eq!(
+            external_ids,
+            [ColorExternalId {
+                color_id: 0,
+                source: "LDraw".to_owned(),
+                external_id: 0,
+                name: Some("Black".to_owned()),
+            }]
+        );
+
+        let requests = server.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| (request.method.as_str(), request.path.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("GET", "/lego/colors/?page_size=1000"),
+                ("GET", "/lego/colors/?page=2&page_size=1000"),
+                ("GET", "/lego/colors/?page=2&page_size=1000"),
+            ]
+        );
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.header("authorization") == Some("key secret"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parts() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route(
+            "/lego/parts/?inc_part_details=1&page_size=1000",
+            TestResponse::json(
+                r#"{
+                    "count": 1,
+                    "next": null,
+                    "previous": null,
+                    "results": [{
+                        "part_num": "3001",
+                        "name": "Brick 2 x 4",
+                        "external_ids": {"BrickLink": ["3001"], "LDraw": ["3001"]}
+                    }]
+                }"#,
+            ),
+        );
+
+        let api = Client::new().api("secret").with_base_url(server.url("/"));
+        let parts = api.parts().await?;
+        let external_ids: Vec<_> = parts.iter().flat_map(|p| p.external_ids()).collect();
+        assert_eq!(
+            external_ids
+                .iter()
+                .map(|id| id.source.as_str())
+                .collect::<Vec<_>>(),
+            ["BrickLink", "LDraw"]
+        );
+        assert_eq!(
+            external_ids[0],
+            PartExternalId {
+                part_num: "3001".to_owned(),
+                source: "BrickLink".to_owned(),
+                external_id: "3001".to_owned(),
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unauthorized() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route("/lego/colors/?page_size=1000", TestResponse::new(401));
+        let api = Client::new().api("wrong").with_base_url(server.url("/"));
+        assert!(api.colors().await.is_err());
+        Ok(())
+    }
+}
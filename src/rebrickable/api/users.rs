@@ -0,0 +1,200 @@
+//! Endpoints of the users API, which require a user token on top of the API
+//! key.
+
+use serde::Deserialize;
+
+use super::Api;
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiSetList {
+    pub id: i64,
+    pub name: String,
+    pub is_buildable: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiSetListSet {
+    pub quantity: i32,
+    pub include_spares: bool,
+    pub set: ApiSetRef,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiSetRef {
+    pub set_num: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiPartList {
+    pub id: i64,
+    pub name: String,
+    pub is_buildable: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiPartListPart {
+    pub quantity: i32,
+    pub part: ApiPartRef,
+    pub color: ApiColorRef,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiPartRef {
+    pub part_num: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiColorRef {
+    pub id: i32,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiLostPart {
+    pub lost_part_id: i64,
+    pub lost_quantity: i32,
+    pub inv_part: ApiInventoryPart,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct ApiInventoryPart {
+    pub set_num: String,
+    pub is_spare: bool,
+    pub part: ApiPartRef,
+    pub color: ApiColorRef,
+}
+
+/// The lists of a user, along with their content.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct UserLists {
+    pub set_lists: Vec<(ApiSetList, Vec<ApiSetListSet>)>,
+    pub part_lists: Vec<(ApiPartList, Vec<ApiPartListPart>)>,
+    pub lost_parts: Vec<ApiLostPart>,
+}
+
+impl Api {
+    pub async fn set_lists(&self, user_token: &str) -> anyhow::Result<Vec<ApiSetList>> {
+        self.get_all(&format!("users/{user_token}/setlists/"), &[])
+            .await
+    }
+
+    pub async fn set_list_sets(
+        &self,
+        user_token: &str,
+        list_id: i64,
+    ) -> anyhow::Result<Vec<ApiSetListSet>> {
+        self.get_all(&format!("users/{user_token}/setlists/{list_id}/sets/"), &[])
+            .await
+    }
+
+    pub async fn part_lists(&self, user_token: &str) -> anyhow::Result<Vec<ApiPartList>> {
+        self.get_all(&format!("users/{user_token}/partlists/"), &[])
+            .await
+    }
+
+    pub async fn part_list_parts(
+        &self,
+        user_token: &str,
+        list_id: i64,
+    ) -> anyhow::Result<Vec<ApiPartListPart>> {
+        self.get_all(
+            &format!("users/{user_token}/partlists/{list_id}/parts/"),
+            &[],
+        )
+        .await
+    }
+
+    pub async fn lost_parts(&self, user_token: &str) -> anyhow::Result<Vec<ApiLostPart>> {
+        self.get_all(&format!("users/{user_token}/lost_parts/"), &[])
+            .await
+    }
+
+    /// Fetches all the set lists, part lists and lost parts of a user.
+    pub async fn user_lists(&self, user_token: &str) -> anyhow::Result<UserLists> {
+        let mut lists = UserLists::default();
+        for set_list in self.set_lists(user_token).await? {
+            let sets = self.set_list_sets(user_token, set_list.id).await?;
+            lists.set_lists.push((set_list, sets));
+        }
+        for part_list in self.part_lists(user_token).await? {
+            let parts = self.part_list_parts(user_token, part_list.id).await?;
+            lists.part_lists.push((part_list, parts));
+        }
+        lists.lost_parts = self.lost_parts(user_token).await?;
+        Ok(lists)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        rebrickable::Client,
+        test_server::{TestResponse, TestServer},
+    };
+
+    fn page(results: &str) -> TestResponse {
+        TestResponse::json(format!(
+            r#"{{"count": 1, "next": null, "previous": null, "results": {results}}}"#
+        ))
+    }
+
+    #[tokio::test]
+    async fn user_lists() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route(
+            "/users/token/setlists/?page_size=1000",
+            page(r#"[{"id": 1, "is_buildable": true, "name": "Owned", "num_sets": 1}]"#),
+        );
+        server.route(
+            "/users/token/setlists/1/sets/?page_size=1000",
+            page(
+                r#"[{
+                    "list_id": 1,
+                    "quantity": 2,
+                    "include_spares": true,
+                    "set": {"set_num": "1000-1", "name": "Fire Station"}
+                }]"#,
+            ),
+        );
+        server.route(
+            "/users/token/partlists/?page_size=1000",
+            page(r#"[{"id": 7, "is_buildable": false, "name": "Wanted", "num_parts": 4}]"#),
+        );
+        server.route(
+            "/users/token/partlists/7/parts/?page_size=1000",
+            page(
+                r#"[{
+                    "list_id": 7,
+                    "quantity": 4,
+                    "part": {"part_num": "3001", "name": "Brick 2 x 4"},
+                    "color": {"id": 4, "name": "Red"}
+                }]"#,
+            ),
+        );
+        server.route(
+            "/users/token/lost_parts/?page_size=1000",
+            page(
+                r#"[{
+                    "lost_part_id": 42,
+                    "lost_quantity": 1,
+                    "inv_part": {
+                        "id": 100,
+                        "set_num": "1000-1",
+                        "quantity": 4,
+                        "is_spare": false,
+                        "part": {"part_num": "3001"},
+                        "color": {"id": 4}
+                    }
+                }]"#,
+            ),
+        );
+
+        let api = Client::new().api("secret").with_base_url(server.url("/"));
+        let lists = api.user_lists("token").await?;
+        assert_eq!(lists.set_lists.len(), 1);
+        assert_eq!(lists.set_lists[0].1[0].set.set_num, "1000-1");
+        assert_eq!(lists.part_lists[0].0.name, "Wanted");
+        assert_eq!(lists.part_lists[0].1[0].color.id, 4);
+        assert_eq!(lists.lost_parts[0].inv_part.part.part_num, "3001");
+        Ok(())
+    }
+}
@@ -1,138 +1,492 @@
-use std::{future::Future, path::Path, sync::Arc, time::Duration};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
 
-use tokio::{fs::File, io::AsyncWriteExt, sync::Semaphore, task::JoinSet};
+use bytes::Bytes;
+use reqwest::StatusCode;
+use tokio::{
+    sync::{Semaphore, mpsc::Sender, oneshot},
+    task::JoinSet,
+};
+use url::Url;
 
-use super::table::{self, Table};
+use super::{
+    api::Api,
+    stream::{ChannelReader, RecordStream, send_batches},
+    table::Table,
+};
+use crate::progress::Progress;
+
+pub const DEFAULT_DOWNLOADS_URL: &str = "https://cdn.rebrickable.com/media/downloads/";
+/// The default maximum number of concurrent downloads.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 4;
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+/// The default number of times a failed fetch is retried.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// The default delay before retrying a failed fetch, doubled on each retry.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// The longest delay to wait for when a server asks to retry later.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// The maximum number of batches of parsed records waiting to be consumed,
+/// per table.
+const BATCHES_CAPACITY: usize = 16;
+/// The maximum number of downloaded chunks waiting to be parsed, per
+/// download.
+pub const CHUNKS_CAPACITY: usize = 256;
 
 #[derive(Clone, Debug)]
 pub struct Client {
-    reqwest: reqwest::Client,
+    pub(super) reqwest: reqwest::Client,
+    downloads_url: Url,
+    max_retries: u32,
+    retry_delay: Duration,
 }
 
 impl Client {
     pub fn new() -> Self {
         Self {
-            reqwest: reqwest::Client::builder()
-                .connect_timeout(Duration::from_secs(10))
-                .timeout(Duration::from_secs(300))
-                .build()
-                .expect("reqwest client builds with default TLS"),
+            reqwest: build_reqwest(DEFAULT_CONNECT_TIMEOUT, DEFAULT_TIMEOUT),
+            downloads_url: Url::parse(DEFAULT_DOWNLOADS_URL)
+                .expect("default downloads URL is valid"),
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
         }
     }
 
-    async fn download_table<T, P>(&self, path: P, timestamp: u64) -> anyhow::Result<()>
+    /// Sets the timeouts to establish a connection, and to receive the next
+    /// piece of a response.
+    pub fn with_timeouts(mut self, connect_timeout: Duration, read_timeout: Duration) -> Self {
+        self.reqwest = build_reqwest(connect_timeout, read_timeout);
+        self
+    }
+
+    /// Sets the number of times a failed fetch is retried, and the delay
+    /// before the first retry.
+    pub fn with_retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Sets the URL the table archives are downloaded from.
+    pub fn with_downloads_url(mut self, downloads_url: Url) -> Self {
+        self.downloads_url = downloads_url;
+        self
+    }
+
+    async fn download_table<T>(
+        &self,
+        timestamp: u64,
+        tx: &Sender<io::Result<Bytes>>,
+    ) -> anyhow::Result<u64>
     where
         T: Table,
-        P: AsRef<Path>,
     {
         // To avoid consistency issues with untimestamped URLs and to avoid scraping the
         // main page for proper timestamps, the simplest solution is to use the
         // current timestamp.
-        let url = format!(
-            "https://cdn.rebrickable.com/media/downloads/{}?{}",
-            T::FILENAME,
-            timestamp
-        );
+        let mut url = self.downloads_url.join(T::FILENAME)?;
+        url.set_query(Some(&timestamp.to_string()));
         tracing::info!("downloading table {}", T::NAME);
-        let mut response = self.reqwest.get(&url).send().await?.error_for_status()?;
+        self.download(T::NAME, url, tx).await
+    }
+
+    /// Returns a fingerprint of the current version of a table archive,
+    /// without downloading it: its ETag, or else its modification date and
+    /// size.
+    pub async fn archive_fingerprint(
+        &self,
+        filename: &str,
+        timestamp: u64,
+    ) -> anyhow::Result<String> {
+        let mut url = self.downloads_url.join(filename)?;
+        url.set_query(Some(&timestamp.to_string()));
+        let response = self.reqwest.head(url).send().await?.error_for_status()?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        if let Some(etag) = header(reqwest::header::ETAG) {
+            return Ok(etag.to_owned());
+        }
+        match (
+            header(reqwest::header::LAST_MODIFIED),
+            header(reqwest::header::CONTENT_LENGTH),
+        ) {
+            (Some(last_modified), Some(length)) => Ok(format!("{last_modified}; {length}")),
+            _ => anyhow::bail!("archive {filename} has neither an ETag nor a modification date"),
+        }
+    }
+
+    /// Downloads a file, sending its chunks as they arrive and returning its
+    /// size. Progress is reported under `name`.
+    ///
+    /// Reading the response waits for the chunks to be consumed once `tx` is
+    /// full. Only the time spent reading counts towards the timeout, so slow
+    /// consumers do not make the download time out.
+    pub async fn download(
+        &self,
+        name: &'static str,
+        url: Url,
+        tx: &Sender<io::Result<Bytes>>,
+    ) -> anyhow::Result<u64> {
+        let mut response = self.reqwest.get(url).send().await?.error_for_status()?;
 
-        let mut file = File::create(&path).await?;
+        let mut progress = Progress::global().download(name, response.content_length());
+        let mut bytes = 0;
         while let Some(chunk) = response.chunk().await? {
-            file.write_all(&chunk).await?;
+            bytes += chunk.len() as u64;
+            progress.inc(chunk.len() as u64);
+            if tx.send(Ok(chunk)).await.is_err() {
+                // The content is no longer needed.
+                break;
+            }
         }
-        file.flush().await?;
 
-        Ok(())
+        Ok(bytes)
     }
 
-    pub fn download_tables<P>(&self, output_dir: P, timestamp: u64) -> DownloadHandler<'_, P>
+    /// Fetches a file into memory.
+    ///
+    /// Connection errors, timeouts, rate limiting and server errors are
+    /// retried with an exponential backoff, unless the server says how long
+    /// to wait.
+    pub async fn fetch(&self, url: Url) -> anyhow::Result<Bytes> {
+        let mut retries = 0;
+        loop {
+            let backoff = self.retry_delay * 2u32.saturating_pow(retries);
+            let delay = match self.reqwest.get(url.clone()).send().await {
+                Ok(response)
+                    if retries < self.max_retries
+                        && (response.status() == StatusCode::TOO_MANY_REQUESTS
+                            || response.status().is_server_error()) =>
+                {
+                    tracing::debug!("fetching {url} failed with {}", response.status());
+                    retry_after(&response).unwrap_or(backoff)
+                }
+                Ok(response) => return Ok(response.error_for_status()?.bytes().await?),
+                Err(err)
+                    if retries < self.max_retries && (err.is_connect() || err.is_timeout()) =>
+                {
+                    tracing::debug!("fetching {url} failed: {err}");
+                    backoff
+                }
+                Err(err) => return Err(err.into()),
+            };
+            tracing::debug!("retrying in {}s", delay.as_secs_f64());
+            tokio::time::sleep(delay).await;
+            retries += 1;
+        }
+    }
+
+    /// Returns a client for the Rebrickable API, authenticated with the given
+    /// key.
+    pub fn api<S>(&self, api_key: S) -> Api
     where
-        P: AsRef<Path>,
+        S: Into<String>,
     {
+        Api::new(self.clone(), api_key.into())
+    }
+
+    pub fn download_tables(&self, timestamp: u64) -> DownloadHandler<'_> {
         DownloadHandler {
             client: self,
-            output_dir,
-            max_concurrency: 4,
+            semaphore: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENCY)),
+            previous_started: None,
             timestamp,
+            max_rejected: 0,
+            tasks: JoinSet::new(),
+            stats: Arc::default(),
         }
     }
 }
 
+fn build_reqwest(connect_timeout: Duration, read_timeout: Duration) -> reqwest::Client {
+    // Responses are streamed to consumers that may stall their reading, so
+    // there is no timeout on whole requests.
+    reqwest::Client::builder()
+        .connect_timeout(connect_timeout)
+        .read_timeout(read_timeout)
+        .build()
+        .expect("reqwest client builds with default TLS")
+}
+
+/// Returns the delay given by the `Retry-After` header of a response, capped
+/// to `MAX_RETRY_AFTER`.
+pub(super) fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?;
+    parse_retry_after(value, SystemTime::now())
+}
+
+/// Parses a `Retry-After` header, given either in seconds or as an HTTP date.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    let delay = match value.parse::<f64>() {
+        Ok(secs) => Duration::try_from_secs_f64(secs).ok()?,
+        // Dates in the past mean that the request can be retried right away.
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(now)
+            .unwrap_or_default(),
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
 impl Default for Client {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Streams tables from the Rebrickable CDN, parsing them on the fly.
+///
+/// Back-pressure is applied through bounded channels, both to the chunks of
+/// the response bodies and to the parsed records, so slower consumers stall
+/// the downloads instead of buffering whole tables in memory.
 #[derive(Debug)]
 #[must_use]
-pub struct DownloadHandler<'a, P> {
+pub struct DownloadHandler<'a> {
     client: &'a Client,
-    output_dir: P,
-    max_concurrency: usize,
+    semaphore: Arc<Semaphore>,
+    /// Notified once the last started download has acquired its permit.
+    previous_started: Option<oneshot::Receiver<()>>,
     timestamp: u64,
+    max_rejected: u64,
+    tasks: JoinSet<anyhow::Result<()>>,
+    stats: Arc<Mutex<Vec<DownloadStats>>>,
 }
 
-impl<P> DownloadHandler<'_, P> {
-    pub async fn execute(self) -> anyhow::Result<()>
+/// Statistics about the download of a table.
+#[derive(Copy, Clone, Debug)]
+pub struct DownloadStats {
+    pub table: &'static str,
+    /// The size of the (compressed) table.
+    pub bytes: u64,
+    pub duration: Duration,
+    /// The number of valid records.
+    pub records: u64,
+    /// The number of invalid records that were skipped.
+    pub rejected: u64,
+}
+
+impl DownloadHandler<'_> {
+    /// Sets the maximum number of concurrent downloads.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.semaphore = Arc::new(Semaphore::new(max_concurrency.max(1)));
+        self
+    }
+
+    /// Sets the number of invalid records skipped per table before giving up.
+    pub fn with_max_rejected(mut self, max_rejected: u64) -> Self {
+        self.max_rejected = max_rejected;
+        self
+    }
+
+    /// Starts downloading a table, returning a stream of its records.
+    ///
+    /// Downloads start in the order this method is called, with a limited
+    /// number of them running concurrently.
+    /// As downloads wait for their records to be consumed, streams must be
+    /// consumed in the same order.
+    pub fn stream<T>(&mut self) -> RecordStream<T::Record>
     where
-        P: AsRef<Path>,
+        T: Table,
+        T::Record: Send + 'static,
     {
-        let output_dir = self.output_dir.as_ref();
-        let semaphore = Arc::new(Semaphore::new(self.max_concurrency));
+        let (bytes_tx, bytes_rx) = tokio::sync::mpsc::channel(CHUNKS_CAPACITY);
+        let (records_tx, records_rx) = std::sync::mpsc::sync_channel(BATCHES_CAPACITY);
 
-        macro_rules! download_task {
-            ($table:ty) => {
-                download_task::<$table>(self.client, &semaphore, output_dir, self.timestamp)
-            };
-        }
+        let index = {
+            let mut stats = self.stats.lock().unwrap();
+            stats.push(DownloadStats {
+                table: T::NAME,
+                bytes: 0,
+                duration: Duration::ZERO,
+                records: 0,
+                rejected: 0,
+            });
+            stats.len() - 1
+        };
+
+        let client = self.client.clone();
+        let semaphore = Arc::clone(&self.semaphore);
+        let (started_tx, started_rx) = oneshot::channel();
+        let previous_started = self.previous_started.replace(started_rx);
+        let timestamp = self.timestamp;
+        let stats = Arc::clone(&self.stats);
+        self.tasks.spawn(async move {
+            // Tasks are not polled in the order they are spawned, so permits
+            // are only requested once the previous download has its own.
+            if let Some(previous_started) = previous_started {
+                // An error means that the previous task was aborted.
+                let _ = previous_started.await;
+            }
+            let _permit = semaphore
+                .acquire()
+                .await
+                .expect("semaphore is never closed");
+            let _ = started_tx.send(());
+            let start = Instant::now();
+            match client.download_table::<T>(timestamp, &bytes_tx).await {
+                Ok(bytes) => {
+                    let stats = &mut stats.lock().unwrap()[index];
+                    stats.bytes = bytes;
+                    stats.duration = start.elapsed();
+                    Ok(())
+                }
+                Err(err) => {
+                    // Make sure the parser does not take a truncated body for a
+                    // complete one.
+                    let _ = bytes_tx.send(Err(io::Error::other(err.to_string()))).await;
+                    Err(err)
+                }
+            }
+        });
+
+        // Decompression and deserialization run on a dedicated thread for
+        // each table, in parallel with the other tables.
+        let max_rejected = self.max_rejected;
+        let stats = Arc::clone(&self.stats);
+        self.tasks.spawn_blocking(move || {
+            let reader = ChannelReader::new(bytes_rx);
+            let waited = reader.waited();
+            let parsed = send_batches(
+                T::NAME,
+                T::read_records_gz(reader),
+                max_rejected,
+                &waited,
+                &records_tx,
+            );
+            tracing::info!(
+                "parsed {} records of table {} in {:.2}s ({:.0} records/s), after waiting {:.2}s for the download",
+                parsed.records,
+                T::NAME,
+                parsed.busy.as_secs_f64(),
+                parsed.records as f64 / parsed.busy.as_secs_f64(),
+                parsed.waited.as_secs_f64(),
+            );
+            let stats = &mut stats.lock().unwrap()[index];
+            stats.records = parsed.records;
+            stats.rejected = parsed.rejected;
+            Ok(())
+        });
 
-        let mut set = JoinSet::new();
-        set.spawn(download_task!(table::Inventories));
-        set.spawn(download_task!(table::InventoryParts));
-        set.spawn(download_task!(table::InventoryMinifigs));
-        set.spawn(download_task!(table::InventorySets));
-        set.spawn(download_task!(table::Parts));
-        set.spawn(download_task!(table::PartCategories));
-        set.spawn(download_task!(table::PartRelationships));
-        set.spawn(download_task!(table::Elements));
-        set.spawn(download_task!(table::Colors));
-        set.spawn(download_task!(table::Minifigs));
-        set.spawn(download_task!(table::Sets));
-        set.spawn(download_task!(table::Themes));
-
-        // Check for any errors.
-        while let Some(res) = set.join_next().await {
+        RecordStream::new(records_rx)
+    }
+
+    /// Returns statistics about the tables, in the order their downloads
+    /// were started.
+    pub fn stats(&self) -> Vec<DownloadStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Waits for all downloads to complete.
+    pub async fn join(&mut self) -> anyhow::Result<()> {
+        while let Some(res) = self.tasks.join_next().await {
             res??;
         }
-
         Ok(())
     }
 }
 
-fn download_task<T>(
-    client: &Client,
-    semaphore: &Arc<Semaphore>,
-    output_dir: &Path,
-    timestamp: u64,
-) -> impl Future<Output = anyhow::Result<()>> + 'static
-where
-    T: Table,
-{
-    let client = client.clone();
-    let semaphore = Arc::clone(semaphore);
-
-    let mut output_path = output_dir.to_path_buf();
-    output_path.push(T::FILENAME);
-
-    async move {
-        let _permit = semaphore
-            .acquire()
-            .await
-            .expect("semaphore is never closed");
-        client
-            .download_table::<T, _>(&output_path, timestamp)
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{Client, MAX_RETRY_AFTER, parse_retry_after};
+    use crate::{
+        rebrickable::table::{
+            Colors, Elements, InventoryParts, PartCategories, Parts, Sets, Themes,
+        },
+        test_server::TestServer,
+    };
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn download_order() -> anyhow::Result<()> {
+        let server = TestServer::start().await?;
+        server.route_tables()?;
+        let downloads_url = server.url("/");
+        // Spawned from a worker thread, tasks are not polled in the order
+        // they are spawned.
+        tokio::spawn(async move {
+            let client = Client::new().with_downloads_url(downloads_url);
+            let mut downloads = client.download_tables(0).with_max_concurrency(1);
+            let streams = (
+                downloads.stream::<Colors>(),
+                downloads.stream::<Themes>(),
+                downloads.stream::<PartCategories>(),
+                downloads.stream::<Parts>(),
+                downloads.stream::<Elements>(),
+                downloads.stream::<Sets>(),
+                downloads.stream::<InventoryParts>(),
+            );
+            tokio::task::spawn_blocking(move || {
+                let (colors, themes, part_categories, parts, elements, sets, inventory_parts) =
+                    streams;
+                colors.count();
+                themes.count();
+                part_categories.count();
+                parts.count();
+                elements.count();
+                sets.count();
+                inventory_parts.count();
+            })
             .await?;
+            downloads.join().await
+        })
+        .await??;
+
+        let requested: Vec<_> = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect();
+        assert_eq!(
+            requested,
+            [
+                "/colors.csv.gz?0",
+                "/themes.csv.gz?0",
+                "/part_categories.csv.gz?0",
+                "/parts.csv.gz?0",
+                "/elements.csv.gz?0",
+                "/sets.csv.gz?0",
+                "/inventory_parts.csv.gz?0",
+            ]
+        );
         Ok(())
     }
+
+    #[test]
+    fn retry_after() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(
+            parse_retry_after(" 1.5 ", now),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(parse_retry_after("86400", now), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("-1", now), None);
+        // Sun, 06 Nov 1994 08:49:37 GMT is `now`.
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:50:07 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after("Mon, 07 Nov 1994 08:49:37 GMT", now),
+            Some(MAX_RETRY_AFTER)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
 }
@@ -0,0 +1,95 @@
+//! User lists in the CSV formats used by Rebrickable's import and export
+//! features.
+
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+/// An entry of a set list, as exported by Rebrickable.
+#[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct SetListEntry {
+    #[serde(rename = "Set Number")]
+    pub set_num: String,
+    #[serde(rename = "Quantity", default = "default_quantity")]
+    pub quantity: u32,
+}
+
+impl SetListEntry {
+    pub fn read_all<R>(rdr: R) -> anyhow::Result<Vec<Self>>
+    where
+        R: Read,
+    {
+        let entries = csv::Reader::from_reader(rdr)
+            .into_deserialize()
+            .collect::<Result<_, _>>()?;
+        Ok(entries)
+    }
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+/// An entry of a parts list, as accepted by Rebrickable's parts list import.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct PartListEntry {
+    #[serde(rename = "Part")]
+    pub part_num: String,
+    #[serde(rename = "Color")]
+    pub color_id: i32,
+    #[serde(rename = "Quantity")]
+    pub quantity: u32,
+}
+
+impl PartListEntry {
+    pub fn write_all<'a, W, I>(wtr: W, entries: I) -> anyhow::Result<()>
+    where
+        W: Write,
+        I: IntoIterator<Item = &'a Self>,
+    {
+        let mut wtr = csv::Writer::from_writer(wtr);
+        for entry in entries {
+            wtr.serialize(entry)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartListEntry, SetListEntry};
+
+    #[test]
+    fn read_set_list() -> anyhow::Result<()> {
+        let csv = "Set Number,Quantity,Includes Spares,Inventory ver\n\
+                   10497-1,2,True,1\n";
+        let entries = SetListEntry::read_all(csv.as_bytes())?;
+        assert_eq!(
+            entries,
+            [SetListEntry {
+                set_num: "10497-1".to_owned(),
+                quantity: 2,
+            }]
+        );
+
+        let entries = SetListEntry::read_all("Set Number\n10497-1\n".as_bytes())?;
+        assert_eq!(entries[0].quantity, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn write_part_list() -> anyhow::Result<()> {
+        let mut buf = Vec::new();
+        PartListEntry::write_all(
+            &mut buf,
+            &[PartListEntry {
+                part_num: "3001".to_owned(),
+                color_id: 4,
+                quantity: 12,
+            }],
+        )?;
+        assert_eq!(String::from_utf8(buf)?, "Part,Color,Quantity\n3001,4,12\n");
+        Ok(())
+    }
+}
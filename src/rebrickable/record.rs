@@ -1,16 +1,27 @@
 use serde::Deserialize;
 use url::Url;
 
-use crate::types::{PartMaterial, PartRelationType, Rgb};
+use crate::types::{InventoryKind, PartMaterial, PartRelationType, Rgb};
 
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Inventory {
     pub id: i32,
     pub version: i32,
+    /// The number of either a set or a minifigure.
     pub set_num: String,
 }
 
+impl Inventory {
+    pub fn kind(&self) -> InventoryKind {
+        if self.set_num.starts_with("fig-") {
+            InventoryKind::Minifig
+        } else {
+            InventoryKind::Set
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct InventoryPart {
@@ -0,0 +1,315 @@
+use std::{
+    io::{self, Read},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant},
+};
+
+use bytes::{Buf, Bytes};
+use tokio::sync::mpsc::Receiver;
+
+/// A blocking reader over chunks of bytes received from an asynchronous task.
+#[derive(Debug)]
+pub struct ChannelReader {
+    rx: Receiver<io::Result<Bytes>>,
+    chunk: Bytes,
+    waited: WaitTime,
+}
+
+impl ChannelReader {
+    pub fn new(rx: Receiver<io::Result<Bytes>>) -> Self {
+        Self {
+            rx,
+            chunk: Bytes::new(),
+            waited: WaitTime::default(),
+        }
+    }
+
+    /// Returns the time spent waiting for chunks, which keeps being updated
+    /// as the reader is used.
+    pub fn waited(&self) -> WaitTime {
+        self.waited.clone()
+    }
+}
+
+/// A time spent waiting, shared between threads.
+#[derive(Clone, Debug, Default)]
+pub struct WaitTime(Arc<AtomicU64>);
+
+impl WaitTime {
+    pub fn get(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+
+    fn add(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.0.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.chunk.has_remaining() {
+            let start = Instant::now();
+            let chunk = self.rx.blocking_recv();
+            self.waited.add(start.elapsed());
+            match chunk {
+                Some(chunk) => self.chunk = chunk?,
+                // The sender is dropped once the whole body is received.
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.remaining());
+        self.chunk.copy_to_slice(&mut buf[..len]);
+        Ok(len)
+    }
+}
+
+/// The number of records sent at once from a parser to its consumer.
+const BATCH_SIZE: usize = 1024;
+
+type Batch<R> = csv::Result<Vec<R>>;
+
+/// Statistics about the records parsed by `send_batches`.
+#[derive(Copy, Clone, Debug, Default)]
+pub(super) struct ParseStats {
+    pub records: u64,
+    pub rejected: u64,
+    /// The time spent parsing, excluding the time spent waiting for the
+    /// input and for the consumer.
+    pub busy: Duration,
+    /// The time spent waiting for the input.
+    pub waited: Duration,
+}
+
+/// Parses records into batches, until the records are exhausted or the
+/// consumer gives up.
+///
+/// Up to `max_rejected` invalid records are skipped with a warning. Errors
+/// reading the input are never skipped. `input_waited` is the time the
+/// records spend waiting for their input, excluded from the parsing time.
+pub(super) fn send_batches<I, R>(
+    table: &str,
+    records: I,
+    max_rejected: u64,
+    input_waited: &WaitTime,
+    tx: &mpsc::SyncSender<Batch<R>>,
+) -> ParseStats
+where
+    I: IntoIterator<Item = csv::Result<R>>,
+{
+    let mut stats = ParseStats::default();
+    let mut records = records.into_iter();
+    loop {
+        let start = Instant::now();
+        let waited = input_waited.get();
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut error = None;
+        for record in records.by_ref() {
+            match record {
+                Ok(record) => batch.push(record),
+                Err(err) if !matches!(err.kind(), csv::ErrorKind::Io(_)) => {
+                    stats.rejected += 1;
+                    if stats.rejected > max_rejected {
+                        tracing::error!(
+                            "too many rejected records in table {table} (maximum: {max_rejected})"
+                        );
+                        error = Some(err);
+                        break;
+                    }
+                    tracing::warn!("rejected record of table {table}: {err}");
+                }
+                Err(err) => {
+                    error = Some(err);
+                    break;
+                }
+            }
+            if batch.len() == BATCH_SIZE {
+                break;
+            }
+        }
+        let waited = input_waited.get().saturating_sub(waited);
+        stats.busy += start.elapsed().saturating_sub(waited);
+        stats.waited += waited;
+        stats.records += batch.len() as u64;
+
+        let is_last = batch.len() < BATCH_SIZE || error.is_some();
+        if !batch.is_empty() && tx.send(Ok(batch)).is_err() {
+            // The consumer gave up.
+            break;
+        }
+        if let Some(err) = error {
+            let _ = tx.send(Err(err));
+            break;
+        }
+        if is_last {
+            break;
+        }
+    }
+    stats
+}
+
+/// The records of a table, as they are parsed.
+#[derive(Debug)]
+pub struct RecordStream<R> {
+    rx: mpsc::Receiver<Batch<R>>,
+    batch: std::vec::IntoIter<R>,
+    count: usize,
+    waited: Duration,
+}
+
+impl<R> RecordStream<R> {
+    pub(super) fn new(rx: mpsc::Receiver<Batch<R>>) -> Self {
+        Self {
+            rx,
+            batch: Vec::new().into_iter(),
+            count: 0,
+            waited: Duration::ZERO,
+        }
+    }
+
+    /// Returns the number of records received so far.
+    pub fn received(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the time spent waiting for records to be parsed.
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+impl<R> Iterator for RecordStream<R> {
+    type Item = csv::Result<R>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.batch.next() {
+                self.count += 1;
+                return Some(Ok(record));
+            }
+            let start = Instant::now();
+            let batch = self.rx.recv().ok();
+            self.waited += start.elapsed();
+            match batch? {
+                Ok(batch) => self.batch = batch.into_iter(),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read},
+        time::Duration,
+    };
+
+    use bytes::Bytes;
+
+    use super::{BATCH_SIZE, ChannelReader, RecordStream, WaitTime, send_batches};
+
+    #[test]
+    fn read_chunks() -> anyhow::Result<()> {
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        tx.blocking_send(Ok(Bytes::from_static(b"hello, ")))?;
+        tx.blocking_send(Ok(Bytes::new()))?;
+        tx.blocking_send(Ok(Bytes::from_static(b"world")))?;
+        drop(tx);
+
+        let mut content = String::new();
+        ChannelReader::new(rx).read_to_string(&mut content)?;
+        assert_eq!(content, "hello, world");
+        Ok(())
+    }
+
+    #[test]
+    fn read_error() -> anyhow::Result<()> {
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        tx.blocking_send(Ok(Bytes::from_static(b"partial")))?;
+        tx.blocking_send(Err(io::Error::other("connection reset")))?;
+
+        let mut content = Vec::new();
+        assert!(ChannelReader::new(rx).read_to_end(&mut content).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn batches() {
+        let (tx, rx) = std::sync::mpsc::sync_channel(16);
+        let records = (0..BATCH_SIZE + 1).map(Ok);
+        let stats = send_batches("test", records, 0, &WaitTime::default(), &tx);
+        assert_eq!(stats.records, BATCH_SIZE as u64 + 1);
+        drop(tx);
+
+        let mut stream = RecordStream::new(rx);
+        let received: Vec<_> = stream.by_ref().map(Result::unwrap).collect();
+        assert_eq!(received, (0..BATCH_SIZE + 1).collect::<Vec<_>>());
+        assert_eq!(stream.received(), BATCH_SIZE + 1);
+    }
+
+    #[test]
+    fn batches_waiting() {
+        let (tx, _rx) = std::sync::mpsc::sync_channel(16);
+        let waited = WaitTime::default();
+        // Each record waits for its input.
+        let records = (0..3).map(|record| {
+            waited.add(Duration::from_secs(1));
+            Ok(record)
+        });
+        let stats = send_batches("test", records, 0, &waited, &tx);
+        assert_eq!(stats.records, 3);
+        assert_eq!(stats.waited, Duration::from_secs(3));
+        assert!(stats.busy < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn read_waiting() -> anyhow::Result<()> {
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        let mut reader = ChannelReader::new(rx);
+        let waited = reader.waited();
+        let sender = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            tx.blocking_send(Ok(Bytes::from_static(b"late")))
+        });
+
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        sender.join().unwrap()?;
+        assert_eq!(content, "late");
+        assert!(waited.get() >= Duration::from_millis(50));
+        Ok(())
+    }
+
+    #[test]
+    fn batches_error() {
+        let (tx, rx) = std::sync::mpsc::sync_channel(16);
+        let csv = "a\n1\nx\n3\n";
+        let records = csv::Reader::from_reader(csv.as_bytes()).into_deserialize::<u32>();
+        let stats = send_batches("test", records, 0, &WaitTime::default(), &tx);
+        assert_eq!((stats.records, stats.rejected), (1, 1));
+        drop(tx);
+
+        let received: Vec<_> = RecordStream::new(rx).collect();
+        assert_eq!(received.len(), 2);
+        assert_eq!(*received[0].as_ref().unwrap(), 1);
+        assert!(received[1].is_err());
+    }
+
+    #[test]
+    fn batches_rejected() {
+        let (tx, rx) = std::sync::mpsc::sync_channel(16);
+        let csv = "a\n1\nx\n3\ny\n";
+        let records = csv::Reader::from_reader(csv.as_bytes()).into_deserialize::<u32>();
+        let stats = send_batches("test", records, 2, &WaitTime::default(), &tx);
+        assert_eq!((stats.records, stats.rejected), (2, 2));
+        drop(tx);
+
+        let received: Vec<_> = RecordStream::new(rx).map(Result::unwrap).collect();
+        assert_eq!(received, [1, 3]);
+    }
+}
@@ -0,0 +1,514 @@
+//! An interactive SQL shell over the database, with shortcuts for common
+//! lookups.
+//!
+//! Inputs are either dot commands (`.set 10497-1`), or SQL statements ending
+//! with a semicolon, possibly spanning several lines. Results are printed as
+//! tables, and the last one can be exported to CSV or JSON.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use rusqlite::types::Value;
+
+use crate::{
+    database::{Database, QueryResult, schema_columns, version_params},
+    dirs,
+    types::InventoryVersion,
+};
+
+const HELP: &str = "\
+.set NUM [VERSION]     Show a set and the parts of an inventory version
+                       (latest, all or a number, defaults to latest)
+.part NUM              Show a part and the colours it appears in
+.color NAME|ID         Show the colours matching a name pattern (with %) or id
+.tables                List the tables and views
+.schema TABLE          Show the definition of a table or view
+.export csv|json FILE  Export the last result
+.help                  Show this help
+.quit                  Exit the shell
+";
+
+const DOT_COMMANDS: [&str; 9] = [
+    ".color", ".exit", ".export", ".help", ".part", ".quit", ".schema", ".set", ".tables",
+];
+
+const SET_QUERY: &str = r#"
+    SELECT s.set_num, s.name, s.year, t.name AS theme, s.num_parts
+    FROM sets AS s
+    JOIN themes AS t ON t.id = s.theme_id
+    WHERE s.set_num IN (?1, ?1 || '-1')
+    ORDER BY s.set_num
+"#;
+
+const SET_PARTS_QUERY: &str = r#"
+    SELECT i.version, ip.part_num, p.name, c.name AS color, ip.quantity, ip.is_spare
+    FROM inventories AS i
+    JOIN inventory_parts AS ip ON ip.inventory_id = i.id
+    JOIN parts AS p ON p.part_num = ip.part_num
+    JOIN colors AS c ON c.id = ip.color_id
+    WHERE i.set_num = ?1 AND (NOT ?2 OR i.is_latest) AND (?3 IS NULL OR i.version = ?3)
+    ORDER BY i.version, ip.is_spare, ip.part_num, c.name
+"#;
+
+const PART_QUERY: &str = r#"
+    SELECT p.part_num, p.name, pc.name AS category, p.part_material
+    FROM parts AS p
+    JOIN part_categories AS pc ON pc.id = p.part_cat_id
+    WHERE p.part_num = ?1
+"#;
+
+const PART_COLORS_QUERY: &str = r#"
+    SELECT
+        c.id,
+        c.name AS color,
+        COUNT(DISTINCT i.set_num) AS num_sets,
+        COUNT(DISTINCT i.fig_num) AS num_minifigs
+    FROM inventory_parts AS ip
+    JOIN inventories AS i ON i.id = ip.inventory_id AND i.is_latest
+    JOIN colors AS c ON c.id = ip.color_id
+    WHERE ip.part_num = ?1 AND NOT ip.is_spare
+    GROUP BY c.id
+    ORDER BY num_sets DESC, c.name
+"#;
+
+const COLOR_QUERY: &str = r#"
+    SELECT id, name, rgb, is_trans, num_parts, num_sets, first_year, last_year
+    FROM colors
+    WHERE name LIKE ?1 OR CAST(id AS TEXT) = ?1
+    ORDER BY id
+"#;
+
+/// What to do after an input.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+#[derive(Debug)]
+pub struct Shell {
+    db: Database,
+    last: Option<QueryResult>,
+}
+
+impl Shell {
+    pub fn new(db: Database) -> Self {
+        Self { db, last: None }
+    }
+
+    /// Runs a complete input, writing its output.
+    pub fn execute<W>(&mut self, input: &str, out: &mut W) -> anyhow::Result<Flow>
+    where
+        W: Write,
+    {
+        let input = input.trim();
+        let Some(command) = input.strip_prefix('.') else {
+            let sql = input.trim_end_matches(';');
+            if !sql.trim().is_empty() {
+                self.show(sql, &[], out)?;
+            }
+            return Ok(Flow::Continue);
+        };
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        match (name, args.as_slice()) {
+            ("quit" | "exit", []) => return Ok(Flow::Quit),
+            ("help", []) => out.write_all(HELP.as_bytes())?,
+            ("tables", []) => {
+                self.show(
+                    "SELECT name, type FROM sqlite_schema \
+                     WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' \
+                     ORDER BY name",
+                    &[],
+                    out,
+                )?;
+            }
+            ("schema", [table]) => {
+                let result = self
+                    .db
+                    .query("SELECT sql FROM sqlite_schema WHERE name = ?1", &[table])?;
+                let Some([Value::Text(sql)]) = result.rows.first().map(Vec::as_slice) else {
+                    anyhow::bail!("no such table: {table}");
+                };
+                writeln!(out, "{sql};")?;
+            }
+            ("set", [set_num]) => self.show_set(set_num, InventoryVersion::Latest, out)?,
+            ("set", [set_num, version]) => self.show_set(set_num, version.parse()?, out)?,
+            ("part", [part_num]) => self.show_part(part_num, out)?,
+            ("color", [_, ..]) => self.show_color(&args.join(" "), out)?,
+            ("export", [format, _, ..]) => {
+                // The path is the rest of the line, as it may contain spaces.
+                let path = command[name.len()..].trim_start()[format.len()..].trim();
+                let Some(result) = &self.last else {
+                    anyhow::bail!("nothing to export, run a query first");
+                };
+                let file = File::create(path)?;
+                match *format {
+                    "csv" => export_csv(result, file)?,
+                    "json" => export_json(result, file)?,
+                    _ => anyhow::bail!("unknown export format {format}, expected csv or json"),
+                }
+                writeln!(out, "exported {} rows to {path}", result.rows.len())?;
+            }
+            _ => anyhow::bail!("unknown or invalid command .{command}, see .help"),
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Prints a set, given with or without its version, and the parts of the
+    /// given versions of its inventory.
+    pub fn show_set<W>(
+        &mut self,
+        set_num: &str,
+        version: InventoryVersion,
+        out: &mut W,
+    ) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        let set = self.show(SET_QUERY, &[&set_num], out)?;
+        let Some(Value::Text(set_num)) = set.rows.first().and_then(|row| row.first()).cloned()
+        else {
+            anyhow::bail!("no such set: {set_num}");
+        };
+        writeln!(out)?;
+        let (latest_only, exact_version) = version_params(version);
+        self.show(
+            SET_PARTS_QUERY,
+            &[&set_num, &latest_only, &exact_version],
+            out,
+        )?;
+        Ok(())
+    }
+
+    /// Prints a part and the colours it appears in.
+    pub fn show_part<W>(&mut self, part_num: &str, out: &mut W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        if self.show(PART_QUERY, &[&part_num], out)?.rows.is_empty() {
+            anyhow::bail!("no such part: {part_num}");
+        }
+        writeln!(out)?;
+        self.show(PART_COLORS_QUERY, &[&part_num], out)?;
+        Ok(())
+    }
+
+    /// Prints the colours whose name matches a `LIKE` pattern, or with an id.
+    pub fn show_color<W>(&mut self, pattern: &str, out: &mut W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        if self.show(COLOR_QUERY, &[&pattern], out)?.rows.is_empty() {
+            anyhow::bail!("no such colour: {pattern}");
+        }
+        Ok(())
+    }
+
+    /// Runs a query and prints its result as a table, keeping it for export.
+    fn show<W>(
+        &mut self,
+        sql: &str,
+        params: &[&dyn rusqlite::ToSql],
+        out: &mut W,
+    ) -> anyhow::Result<&QueryResult>
+    where
+        W: Write,
+    {
+        let result = self.db.query(sql, params)?;
+        write_table(&result, out)?;
+        Ok(self.last.insert(result))
+    }
+}
+
+/// Returns whether an input can be executed: a dot command, or SQL ending with
+/// a semicolon.
+pub fn is_complete(input: &str) -> bool {
+    let input = input.trim();
+    input.is_empty() || input.starts_with('.') || input.ends_with(';')
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s.clone(),
+        Value::Blob(b) => format!("<{} bytes>", b.len()),
+    }
+}
+
+/// Writes a result as a table with aligned columns, numbers to the right.
+fn write_table<W>(result: &QueryResult, out: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    let cells: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(text).collect())
+        .collect();
+    let widths: Vec<usize> = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let header: Vec<String> = result
+        .columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, &width)| format!("{column:<width$}"))
+        .collect();
+    writeln!(out, "{}", header.join("  ").trim_end())?;
+    let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+    writeln!(out, "{}", rule.join("  "))?;
+    for (row, values) in cells.iter().zip(&result.rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(values)
+            .zip(&widths)
+            .map(|((cell, value), &width)| match value {
+                Value::Integer(_) | Value::Real(_) => format!("{cell:>width$}"),
+                _ => format!("{cell:<width$}"),
+            })
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    let count = result.rows.len();
+    writeln!(out, "({count} row{})", if count == 1 { "" } else { "s" })
+}
+
+fn export_csv<W>(result: &QueryResult, wtr: W) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    let mut wtr = csv::Writer::from_writer(wtr);
+    wtr.write_record(&result.columns)?;
+    for row in &result.rows {
+        wtr.write_record(row.iter().map(text))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes the rows as JSON objects.
+///
+/// Repeated column names, as in joins, are numbered from the second one on,
+/// e.g. `name` and `name:1`.
+fn export_json<W>(result: &QueryResult, wtr: W) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    let mut keys = BTreeSet::new();
+    let columns: Vec<String> = result
+        .columns
+        .iter()
+        .map(|column| {
+            let mut key = column.clone();
+            let mut n = 0;
+            while keys.contains(&key) {
+                n += 1;
+                key = format!("{column}:{n}");
+            }
+            keys.insert(key.clone());
+            key
+        })
+        .collect();
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = result
+        .rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .cloned()
+                .zip(row.iter().map(|value| match value {
+                    Value::Null => serde_json::Value::Null,
+                    Value::Integer(i) => (*i).into(),
+                    Value::Real(f) => (*f).into(),
+                    Value::Text(s) => s.clone().into(),
+                    Value::Blob(b) => b.clone().into(),
+                }))
+                .collect()
+        })
+        .collect();
+    serde_json::to_writer_pretty(wtr, &rows)?;
+    Ok(())
+}
+
+/// Completes dot commands, and table and column names from the schema.
+#[derive(Clone, Debug)]
+pub struct Completer {
+    columns: BTreeMap<String, Vec<String>>,
+    names: BTreeSet<String>,
+}
+
+impl Completer {
+    pub fn new() -> anyhow::Result<Self> {
+        let columns = schema_columns()?;
+        let names = columns
+            .iter()
+            .flat_map(|(table, columns)| [table].into_iter().chain(columns))
+            .cloned()
+            .collect();
+        Ok(Self { columns, names })
+    }
+
+    /// Returns the start of the word before the cursor and its completions.
+    ///
+    /// After `table.`, only the columns of the table are completed.
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = before
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .map_or(0, |i| i + 1);
+        let word = &before[start..];
+        if start == 0 && word.starts_with('.') {
+            let commands = DOT_COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(|command| command.to_string())
+                .collect();
+            return (start, commands);
+        }
+        if let Some((table, prefix)) = word.rsplit_once('.') {
+            let columns = self
+                .columns
+                .get(table)
+                .into_iter()
+                .flatten()
+                .filter(|column| column.starts_with(prefix))
+                .cloned()
+                .collect();
+            return (start + table.len() + 1, columns);
+        }
+        let names = self
+            .names
+            .range(word.to_owned()..)
+            .take_while(|name| name.starts_with(word))
+            .cloned()
+            .collect();
+        (start, names)
+    }
+}
+
+/// Returns `$XDG_STATE_HOME/rbk-db/history`, or
+/// `~/.local/state/rbk-db/history`.
+pub fn default_history_path() -> Option<PathBuf> {
+    Some(dirs::xdg_dir("XDG_STATE_HOME", ".local/state")?.join("history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{Completer, Flow, Shell};
+    use crate::database::tests::open_fixture;
+
+    fn run(shell: &mut Shell, input: &str) -> anyhow::Result<String> {
+        let mut out = Vec::new();
+        assert_eq!(shell.execute(input, &mut out)?, Flow::Continue);
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn execute() -> anyhow::Result<()> {
+        let mut shell = Shell::new(open_fixture()?);
+        assert_eq!(
+            run(
+                &mut shell,
+                "SELECT set_num, name, num_parts\nFROM sets ORDER BY set_num;"
+            )?,
+            "set_num  name          num_parts\n\
+             -------  ------------  ---------\n\
+             1000-1   Fire Station         10\n\
+             1001-1   Fire Cart             3\n\
+             (2 rows)\n"
+        );
+
+        let set = run(&mut shell, ".set 1000")?;
+        assert!(set.contains("1000-1   Fire Station  2020  Fire"));
+        assert!(set.contains("      2  3001      Brick 2 x 4  Red           4         0\n"));
+        let dir = tempdir()?;
+        let path = dir.path().join("set parts.csv");
+        run(&mut shell, &format!(".export csv {}", path.display()))?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "version,part_num,name,color,quantity,is_spare\n\
+             2,3001,Brick 2 x 4,Red,4,0\n\
+             2,3001,Brick 2 x 4,Red,1,1\n"
+        );
+
+        assert!(
+            run(&mut shell, ".set 1000-1 1")?
+                .contains("      1  3001      Brick 2 x 4  Red           5         0\n")
+        );
+        assert!(run(&mut shell, ".set 1000-1 all")?.contains("(3 rows)"));
+        assert!(shell.execute(".set 1000-1 first", &mut Vec::new()).is_err());
+        assert!(run(&mut shell, ".part 3001")?.contains(" 4  Red           1             0\n"));
+        assert!(run(&mut shell, ".color red")?.contains("c91a09"));
+        let path = dir.path().join("colors.json");
+        run(&mut shell, &format!(".export json {}", path.display()))?;
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(json[0]["name"], "Red");
+        assert_eq!(json[0]["first_year"], 1950);
+
+        // Repeated column names are kept apart.
+        run(
+            &mut shell,
+            "SELECT sets.name, themes.name FROM sets JOIN themes ON themes.id = sets.theme_id;",
+        )?;
+        let path = dir.path().join("sets.json");
+        run(&mut shell, &format!(".export json {}", path.display()))?;
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(
+            json[0],
+            serde_json::json!({"name": "Fire Station", "name:1": "Fire"})
+        );
+
+        assert!(shell.execute(".set 9999-1", &mut Vec::new()).is_err());
+        assert!(shell.execute(".frobnicate", &mut Vec::new()).is_err());
+        assert_eq!(shell.execute(".quit", &mut Vec::new())?, Flow::Quit);
+        Ok(())
+    }
+
+    #[test]
+    fn is_complete() {
+        assert!(super::is_complete(".tables"));
+        assert!(super::is_complete("SELECT 1;"));
+        assert!(!super::is_complete("SELECT *\nFROM sets"));
+    }
+
+    #[test]
+    fn complete() -> anyhow::Result<()> {
+        let completer = Completer::new()?;
+        assert_eq!(
+            completer.complete(".ex", 3),
+            (0, vec![".exit".to_owned(), ".export".to_owned()])
+        );
+        assert_eq!(
+            completer.complete("SELECT * FROM inventory_m", 25),
+            (14, vec!["inventory_minifigs".to_owned()])
+        );
+        assert_eq!(
+            completer.complete("SELECT themes.par", 17),
+            (14, vec!["parent_id".to_owned()])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,690 @@
+//! Static HTML catalogue, browsable offline.
+//!
+//! The site has an index page with the tree of themes, a page per theme, set,
+//! minifigure and part, and a search page filtering an index of all of them
+//! client-side. Pages only link to each other with relative URLs, so the site
+//! works from the file system as well as from any web server.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::database::{
+    Catalog, CatalogMinifig, CatalogPart, CatalogSet, CatalogTheme, InventoryPart,
+};
+
+const STYLE: &str = include_str!("site/style.css");
+const SEARCH_SCRIPT: &str = include_str!("site/search.js");
+/// The maximum depth of the themes rendered below the root themes.
+const MAX_THEME_DEPTH: usize = 32;
+
+/// Statistics about a generated site.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct SiteStats {
+    pub pages: usize,
+    /// The number of images copied from the local cache.
+    pub images: usize,
+}
+
+/// Generates the site into a directory, overwriting existing pages.
+///
+/// Images fetched into the local cache are copied along the pages, while the
+/// others are linked to their original URL.
+pub fn generate(catalog: &Catalog, title: &str, out_dir: &Path) -> anyhow::Result<SiteStats> {
+    let mut site = Site::new(catalog, title, out_dir);
+    for dir in ["themes", "sets", "minifigs", "parts", "images"] {
+        fs::create_dir_all(out_dir.join(dir))?;
+    }
+    site.copy_images()?;
+    site.write("style.css", STYLE)?;
+    site.write("search.js", SEARCH_SCRIPT)?;
+    site.write_search_index()?;
+    site.write_page("search.html", "Search", SEARCH_BODY)?;
+    site.write_index()?;
+    for theme in &catalog.themes {
+        site.write_theme(theme)?;
+    }
+    for set in &catalog.sets {
+        site.write_set(set)?;
+    }
+    for minifig in &catalog.minifigs {
+        site.write_minifig(minifig)?;
+    }
+    for part in &catalog.parts {
+        site.write_part(part)?;
+    }
+    Ok(site.stats)
+}
+
+const SEARCH_BODY: &str = r#"<h1>Search</h1>
+<p><input id="q" type="search" placeholder="Number or name" autofocus></p>
+<ul id="results"></ul>
+<script src="search-index.js"></script>
+<script src="search.js"></script>
+"#;
+
+/// Where a part is used.
+struct Usage<'a> {
+    href: String,
+    id: &'a str,
+    name: &'a str,
+    part: &'a InventoryPart,
+}
+
+struct Site<'a> {
+    catalog: &'a Catalog,
+    title: &'a str,
+    out_dir: &'a Path,
+    themes: BTreeMap<i32, &'a CatalogTheme>,
+    sub_themes: BTreeMap<Option<i32>, Vec<&'a CatalogTheme>>,
+    theme_sets: BTreeMap<i32, Vec<&'a CatalogSet>>,
+    minifigs: BTreeMap<&'a str, &'a CatalogMinifig>,
+    parts: BTreeMap<&'a str, &'a CatalogPart>,
+    minifig_sets: BTreeMap<&'a str, Vec<(&'a CatalogSet, u32)>>,
+    part_usages: BTreeMap<&'a str, Vec<Usage<'a>>>,
+    /// The file names of the images copied from the cache, by URL.
+    local_images: BTreeMap<&'a str, String>,
+    stats: SiteStats,
+}
+
+impl<'a> Site<'a> {
+    fn new(catalog: &'a Catalog, title: &'a str, out_dir: &'a Path) -> Self {
+        let themes = catalog
+            .themes
+            .iter()
+            .map(|theme| (theme.id, theme))
+            .collect();
+        let mut sub_themes: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for theme in &catalog.themes {
+            sub_themes.entry(theme.parent_id).or_default().push(theme);
+        }
+        let mut theme_sets: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for set in &catalog.sets {
+            theme_sets.entry(set.theme_id).or_default().push(set);
+        }
+        let minifigs = catalog
+            .minifigs
+            .iter()
+            .map(|minifig| (minifig.fig_num.as_str(), minifig))
+            .collect();
+        let parts = catalog
+            .parts
+            .iter()
+            .map(|part| (part.part_num.as_str(), part))
+            .collect();
+
+        let mut minifig_sets: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        let mut part_usages: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for set in &catalog.sets {
+            for minifig in catalog.set_minifigs.get(&set.set_num).into_iter().flatten() {
+                let sets = minifig_sets.entry(minifig.fig_num.as_str()).or_default();
+                // Sets are listed once, whatever the number of their versions.
+                if !sets
+                    .iter()
+                    .any(|(s, _): &(&CatalogSet, _)| s.set_num == set.set_num)
+                {
+                    sets.push((set, minifig.quantity));
+                }
+            }
+            for part in catalog.set_parts.get(&set.set_num).into_iter().flatten() {
+                part_usages
+                    .entry(part.part_num.as_str())
+                    .or_default()
+                    .push(Usage {
+                        href: page_href("../", "sets", &set.set_num),
+                        id: &set.set_num,
+                        name: &set.name,
+                        part,
+                    });
+            }
+        }
+        for minifig in &catalog.minifigs {
+            for part in catalog
+                .minifig_parts
+                .get(&minifig.fig_num)
+                .into_iter()
+                .flatten()
+            {
+                part_usages
+                    .entry(part.part_num.as_str())
+                    .or_default()
+                    .push(Usage {
+                        href: page_href("../", "minifigs", &minifig.fig_num),
+                        id: &minifig.fig_num,
+                        name: &minifig.name,
+                        part,
+                    });
+            }
+        }
+
+        Self {
+            catalog,
+            title,
+            out_dir,
+            themes,
+            sub_themes,
+            theme_sets,
+            minifigs,
+            parts,
+            minifig_sets,
+            part_usages,
+            local_images: BTreeMap::new(),
+            stats: SiteStats::default(),
+        }
+    }
+
+    fn copy_images(&mut self) -> anyhow::Result<()> {
+        for (url, path) in &self.catalog.cached_images {
+            let path = Path::new(path);
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            if !path.exists() {
+                tracing::debug!("cached image {} is missing", path.display());
+                continue;
+            }
+            let name = name.to_string_lossy().into_owned();
+            let target = self.out_dir.join("images").join(&name);
+            if !target.exists() {
+                fs::copy(path, target)?;
+                self.stats.images += 1;
+            }
+            self.local_images.insert(url, name);
+        }
+        Ok(())
+    }
+
+    fn write(&self, path: &str, content: &str) -> anyhow::Result<()> {
+        let path: PathBuf = self.out_dir.join(path);
+        fs::write(&path, content)
+            .map_err(|err| anyhow::anyhow!("failed to write {}: {err}", path.display()))
+    }
+
+    /// Writes a page, wrapped in the common layout.
+    fn write_page(&mut self, path: &str, title: &str, body: &str) -> anyhow::Result<()> {
+        let root = if path.contains('/') { "../" } else { "" };
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} - {site_title}</title>
+<link rel="stylesheet" href="{root}style.css">
+</head>
+<body>
+<header>
+<a class="home" href="{root}index.html">{site_title}</a>
+<form action="{root}search.html"><input name="q" type="search" placeholder="Search"></form>
+</header>
+<main>
+{body}</main>
+</body>
+</html>
+"#,
+            title = escape(title),
+            site_title = escape(self.title),
+        );
+        self.write(path, &html)?;
+        self.stats.pages += 1;
+        Ok(())
+    }
+
+    fn write_search_index(&self) -> anyhow::Result<()> {
+        let mut entries = Vec::new();
+        for theme in &self.catalog.themes {
+            entries.push(
+                [
+                    "theme",
+                    &theme.id.to_string(),
+                    &theme.name,
+                    &theme_href("", theme.id),
+                ]
+                .map(str::to_owned),
+            );
+        }
+        for set in &self.catalog.sets {
+            entries.push(
+                [
+                    "set",
+                    &set.set_num,
+                    &set.name,
+                    &page_href("", "sets", &set.set_num),
+                ]
+                .map(str::to_owned),
+            );
+        }
+        for minifig in &self.catalog.minifigs {
+            entries.push(
+                [
+                    "minifig",
+                    &minifig.fig_num,
+                    &minifig.name,
+                    &page_href("", "minifigs", &minifig.fig_num),
+                ]
+                .map(str::to_owned),
+            );
+        }
+        for part in &self.catalog.parts {
+            entries.push(
+                [
+                    "part",
+                    &part.part_num,
+                    &part.name,
+                    &page_href("", "parts", &part.part_num),
+                ]
+                .map(str::to_owned),
+            );
+        }
+        // A script rather than JSON, as browsers do not let pages opened from
+        // the file system fetch other files.
+        let script = format!(
+            "const SEARCH_INDEX = {};\n",
+            serde_json::to_string(&entries)?
+        );
+        self.write("search-index.js", &script)
+    }
+
+    fn write_index(&mut self) -> anyhow::Result<()> {
+        let mut body = String::new();
+        let _ = writeln!(body, "<h1>{}</h1>", escape(self.title));
+        let _ = writeln!(
+            body,
+            "<p>{} themes, {} sets, {} minifigures and {} parts.</p>",
+            self.catalog.themes.len(),
+            self.catalog.sets.len(),
+            self.catalog.minifigs.len(),
+            self.catalog.parts.len()
+        );
+        body.push_str("<h2>Themes</h2>\n");
+        self.theme_tree(&mut body, "", None, 0);
+        self.write_page("index.html", "Themes", &body)
+    }
+
+    /// Renders the sub-themes of a theme, recursively.
+    fn theme_tree(&self, body: &mut String, root: &str, parent_id: Option<i32>, depth: usize) {
+        // The depth is bounded in case of a cycle in the data.
+        if depth >= MAX_THEME_DEPTH {
+            return;
+        }
+        let Some(themes) = self.sub_themes.get(&parent_id) else {
+            return;
+        };
+        body.push_str("<ul class=\"themes\">\n");
+        for theme in themes {
+            let num_sets = self.theme_sets.get(&theme.id).map_or(0, Vec::len);
+            let _ = write!(
+                body,
+                "<li><a href=\"{}\">{}</a> ({num_sets})",
+                theme_href(root, theme.id),
+                escape(&theme.name)
+            );
+            self.theme_tree(body, root, Some(theme.id), depth + 1);
+            body.push_str("</li>\n");
+        }
+        body.push_str("</ul>\n");
+    }
+
+    /// Renders links to a theme and its ancestors, from the root.
+    fn theme_path(&self, theme_id: i32) -> String {
+        let mut links = Vec::new();
+        let mut next = self.themes.get(&theme_id);
+        // The depth is bounded in case of a cycle in the data.
+        while let Some(theme) = next.filter(|_| links.len() < MAX_THEME_DEPTH) {
+            links.push(format!(
+                "<a href=\"{}\">{}</a>",
+                theme_href("../", theme.id),
+                escape(&theme.name)
+            ));
+            next = theme.parent_id.and_then(|id| self.themes.get(&id));
+        }
+        links.reverse();
+        links.join(" &gt; ")
+    }
+
+    fn image(&self, url: &str, class: &str) -> String {
+        let src = match self.local_images.get(url) {
+            Some(name) => format!("../images/{name}"),
+            None if url.is_empty() => return String::new(),
+            None => url.to_owned(),
+        };
+        format!(
+            "<img class=\"{class}\" src=\"{}\" alt=\"\" loading=\"lazy\">",
+            escape(&src)
+        )
+    }
+
+    fn swatch(&self, color_id: i32) -> String {
+        match self.catalog.colors.get(&color_id) {
+            Some(color) => format!(
+                "<span class=\"swatch{}\" style=\"background: {}\"></span> {}",
+                if color.is_trans { " trans" } else { "" },
+                color.rgb,
+                escape(&color.name)
+            ),
+            None => format!("colour {color_id}"),
+        }
+    }
+
+    fn write_theme(&mut self, theme: &CatalogTheme) -> anyhow::Result<()> {
+        let mut body = String::new();
+        let _ = writeln!(
+            body,
+            "<nav class=\"breadcrumb\">{}</nav>",
+            self.theme_path(theme.id)
+        );
+        let _ = writeln!(body, "<h1>{}</h1>", escape(&theme.name));
+        if self.sub_themes.contains_key(&Some(theme.id)) {
+            body.push_str("<h2>Sub-themes</h2>\n");
+            self.theme_tree(&mut body, "../", Some(theme.id), 0);
+        }
+        if let Some(sets) = self.theme_sets.get(&theme.id) {
+            body.push_str("<h2>Sets</h2>\n<table>\n");
+            body.push_str(
+                "<tr><th></th><th>Number</th><th>Name</th><th>Year</th><th>Parts</th></tr>\n",
+            );
+            for set in sets {
+                let _ = writeln!(
+                    body,
+                    "<tr><td>{}</td><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td class=\"number\">{}</td></tr>",
+                    self.image(&set.img_url, "thumbnail"),
+                    page_href("../", "sets", &set.set_num),
+                    escape(&set.set_num),
+                    escape(&set.name),
+                    set.year,
+                    set.num_parts
+                );
+            }
+            body.push_str("</table>\n");
+        }
+        self.write_page(&theme_href("", theme.id), &theme.name, &body)
+    }
+
+    fn inventory_table(&self, body: &mut String, parts: &[InventoryPart]) {
+        body.push_str("<table>\n");
+        body.push_str("<tr><th></th><th>Colour</th><th>Part</th><th>Name</th><th>Quantity</th><th>Spare</th></tr>\n");
+        for part in parts {
+            let name = self
+                .parts
+                .get(part.part_num.as_str())
+                .map_or("", |p| p.name.as_str());
+            let _ = writeln!(
+                body,
+                "<tr><td>{}</td><td>{}</td><td><a href=\"{}\">{}</a></td><td>{}</td><td class=\"number\">{}</td><td>{}</td></tr>",
+                part.img_url
+                    .as_deref()
+                    .map_or_else(String::new, |url| self.image(url, "thumbnail")),
+                self.swatch(part.color_id),
+                page_href("../", "parts", &part.part_num),
+                escape(&part.part_num),
+                escape(name),
+                part.quantity,
+                if part.is_spare { "yes" } else { "" }
+            );
+        }
+        body.push_str("</table>\n");
+    }
+
+    fn write_set(&mut self, set: &CatalogSet) -> anyhow::Result<()> {
+        let mut body = String::new();
+        let _ = writeln!(
+            body,
+            "<nav class=\"breadcrumb\">{}</nav>",
+            self.theme_path(set.theme_id)
+        );
+        let _ = writeln!(
+            body,
+            "<h1>{} {}</h1>",
+            escape(&set.set_num),
+            escape(&set.name)
+        );
+        body.push_str(&self.image(&set.img_url, "picture"));
+        let _ = writeln!(
+            body,
+            "\n<p>Released in {}, {} parts.</p>",
+            set.year, set.num_parts
+        );
+        let multiple_versions = self.multiple_versions(set);
+        let minifigs = self.catalog.set_minifigs.get(&set.set_num);
+        for minifigs in minifigs
+            .into_iter()
+            .flat_map(|m| m.chunk_by(|a, b| a.version == b.version))
+        {
+            body.push_str(&heading(
+                "Minifigures",
+                minifigs[0].version,
+                multiple_versions,
+            ));
+            body.push_str("<table>\n");
+            for inventory_minifig in minifigs {
+                let fig_num = &inventory_minifig.fig_num;
+                let minifig = self.minifigs.get(fig_num.as_str());
+                let _ = writeln!(
+                    body,
+                    "<tr><td>{}</td><td><a href=\"{}\">{}</a></td><td>{}</td><td class=\"number\">{}</td></tr>",
+                    minifig.map_or_else(String::new, |m| self.image(&m.img_url, "thumbnail")),
+                    page_href("../", "minifigs", fig_num),
+                    escape(fig_num),
+                    escape(minifig.map_or("", |m| m.name.as_str())),
+                    inventory_minifig.quantity,
+                );
+            }
+            body.push_str("</table>\n");
+        }
+        let parts = self.catalog.set_parts.get(&set.set_num);
+        for parts in parts
+            .into_iter()
+            .flat_map(|p| p.chunk_by(|a, b| a.version == b.version))
+        {
+            body.push_str(&heading("Parts", parts[0].version, multiple_versions));
+            self.inventory_table(&mut body, parts);
+        }
+        self.write_page(
+            &format!("sets/{}", file_name(&set.set_num)),
+            &format!("{} {}", set.set_num, set.name),
+            &body,
+        )
+    }
+
+    /// Returns whether several versions of the inventory of a set are shown.
+    fn multiple_versions(&self, set: &CatalogSet) -> bool {
+        let versions: BTreeSet<u32> = (self.catalog.set_parts.get(&set.set_num).into_iter())
+            .flatten()
+            .map(|part| part.version)
+            .chain(
+                (self.catalog.set_minifigs.get(&set.set_num).into_iter())
+                    .flatten()
+                    .map(|minifig| minifig.version),
+            )
+            .collect();
+        versions.len() > 1
+    }
+
+    fn write_minifig(&mut self, minifig: &CatalogMinifig) -> anyhow::Result<()> {
+        let mut body = String::new();
+        let _ = writeln!(
+            body,
+            "<h1>{} {}</h1>",
+            escape(&minifig.fig_num),
+            escape(&minifig.name)
+        );
+        body.push_str(&self.image(&minifig.img_url, "picture"));
+        let _ = writeln!(body, "\n<p>{} parts.</p>", minifig.num_parts);
+        if let Some(sets) = self.minifig_sets.get(minifig.fig_num.as_str()) {
+            body.push_str("<h2>Sets</h2>\n<table>\n");
+            for (set, quantity) in sets {
+                let _ = writeln!(
+                    body,
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td class=\"number\">{quantity}</td></tr>",
+                    page_href("../", "sets", &set.set_num),
+                    escape(&set.set_num),
+                    escape(&set.name),
+                );
+            }
+            body.push_str("</table>\n");
+        }
+        if let Some(parts) = self.catalog.minifig_parts.get(&minifig.fig_num) {
+            body.push_str("<h2>Parts</h2>\n");
+            self.inventory_table(&mut body, parts);
+        }
+        self.write_page(
+            &format!("minifigs/{}", file_name(&minifig.fig_num)),
+            &format!("{} {}", minifig.fig_num, minifig.name),
+            &body,
+        )
+    }
+
+    fn write_part(&mut self, part: &CatalogPart) -> anyhow::Result<()> {
+        let mut body = String::new();
+        let _ = writeln!(
+            body,
+            "<h1>{} {}</h1>\n<p>{}</p>",
+            escape(&part.part_num),
+            escape(&part.name),
+            escape(&part.category)
+        );
+        if let Some(usages) = self.part_usages.get(part.part_num.as_str()) {
+            let colors: BTreeSet<i32> = usages.iter().map(|usage| usage.part.color_id).collect();
+            body.push_str("<h2>Colours</h2>\n<ul>\n");
+            for color_id in colors {
+                let _ = writeln!(body, "<li>{}</li>", self.swatch(color_id));
+            }
+            body.push_str("</ul>\n<h2>Appears in</h2>\n<table>\n");
+            for usage in usages {
+                let _ = writeln!(
+                    body,
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td class=\"number\">{}</td><td>{}</td></tr>",
+                    usage.href,
+                    escape(usage.id),
+                    escape(usage.name),
+                    self.swatch(usage.part.color_id),
+                    usage.part.quantity,
+                    if usage.part.is_spare { "spare" } else { "" }
+                );
+            }
+            body.push_str("</table>\n");
+        }
+        self.write_page(
+            &format!("parts/{}", file_name(&part.part_num)),
+            &format!("{} {}", part.part_num, part.name),
+            &body,
+        )
+    }
+}
+
+/// Returns the heading of a section of an inventory, naming its version when
+/// several are shown.
+fn heading(title: &str, version: u32, multiple_versions: bool) -> String {
+    if multiple_versions {
+        format!("<h2>{title} (version {version})</h2>\n")
+    } else {
+        format!("<h2>{title}</h2>\n")
+    }
+}
+
+fn theme_href(root: &str, id: i32) -> String {
+    format!("{root}themes/{id}.html")
+}
+
+fn page_href(root: &str, dir: &str, id: &str) -> String {
+    format!("{root}{dir}/{}", file_name(id))
+}
+
+/// Returns the file name of the page of a set, minifigure or part.
+///
+/// Characters that are not safe in file names and URLs are escaped, keeping
+/// names distinct.
+fn file_name(id: &str) -> String {
+    let mut name = String::with_capacity(id.len() + 5);
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.' {
+            name.push(char::from(byte));
+        } else {
+            let _ = write!(name, "_{byte:02x}");
+        }
+    }
+    name.push_str(".html");
+    name
+}
+
+/// Escapes text for HTML and XML content and attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::{database::tests::open_fixture, types::InventoryVersion};
+
+    #[test]
+    fn file_name() {
+        assert_eq!(super::file_name("3001"), "3001.html");
+        assert_eq!(super::file_name("10497-1"), "10497-1.html");
+        assert_eq!(
+            super::file_name("970c00pr0001/a_b"),
+            "970c00pr0001_2fa_5fb.html"
+        );
+    }
+
+    #[test]
+    fn generate() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let db = open_fixture()?;
+        let catalog = db.catalog(InventoryVersion::Latest)?;
+        let stats = super::generate(&catalog, "Club <catalogue>", dir.path())?;
+        // Index, search, 3 themes, 2 sets, 1 minifigure and 11 parts.
+        assert_eq!(stats.pages, 19);
+        assert_eq!(stats.images, 0);
+
+        let index = fs::read_to_string(dir.path().join("index.html"))?;
+        assert!(index.contains("<title>Themes - Club &lt;catalogue&gt;</title>"));
+        assert!(index.contains(
+            "<li><a href=\"themes/1.html\">Town</a> (0)<ul class=\"themes\">\n\
+             <li><a href=\"themes/2.html\">Fire</a> (2)</li>"
+        ));
+
+        let set = fs::read_to_string(dir.path().join("sets/1000-1.html"))?;
+        assert!(set.contains(
+            "<a href=\"../themes/1.html\">Town</a> &gt; <a href=\"../themes/2.html\">Fire</a>"
+        ));
+        assert!(set.contains("<span class=\"swatch\" style=\"background: #c91a09\"></span> Red"));
+        assert!(set.contains("<a href=\"../parts/3001.html\">3001</a>"));
+        assert!(set.contains("<a href=\"../minifigs/fig-000001.html\">fig-000001</a>"));
+
+        let part = fs::read_to_string(dir.path().join("parts/3001.html"))?;
+        assert!(part.contains("<a href=\"../sets/1000-1.html\">1000-1</a>"));
+        let minifig = fs::read_to_string(dir.path().join("minifigs/fig-000001.html"))?;
+        assert!(minifig.contains("<a href=\"../sets/1000-1.html\">1000-1</a>"));
+
+        let search_index = fs::read_to_string(dir.path().join("search-index.js"))?;
+        assert!(search_index.contains(r#"["set","1001-1","Fire Cart","sets/1001-1.html"]"#));
+        assert!(!set.contains("version"));
+
+        // Each version of an inventory has its own sections.
+        let dir = tempdir()?;
+        super::generate(&db.catalog(InventoryVersion::All)?, "All", dir.path())?;
+        let set = fs::read_to_string(dir.path().join("sets/1000-1.html"))?;
+        assert!(set.contains("<h2>Parts (version 1)</h2>"));
+        assert!(set.contains("<h2>Minifigures (version 2)</h2>"));
+        assert!(set.contains("<h2>Parts (version 2)</h2>"));
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+// Filters the search index, loaded beforehand as `SEARCH_INDEX`: an array of
+// [kind, identifier, name, href] entries.
+(function () {
+    const MAX_RESULTS = 200;
+    const input = document.getElementById("q");
+    const results = document.getElementById("results");
+
+    function search() {
+        const terms = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+        results.replaceChildren();
+        if (terms.length === 0) {
+            return;
+        }
+        let count = 0;
+        for (const [kind, id, name, href] of SEARCH_INDEX) {
+            const text = (id + " " + name).toLowerCase();
+            if (!terms.every((term) => text.includes(term))) {
+                continue;
+            }
+            const link = document.createElement("a");
+            link.href = href;
+            link.textContent = id + " " + name;
+            const item = document.createElement("li");
+            item.append(kind + ": ", link);
+            results.append(item);
+            if (++count >= MAX_RESULTS) {
+                break;
+            }
+        }
+    }
+
+    input.value = new URLSearchParams(location.search).get("q") || "";
+    input.addEventListener("input", search);
+    search();
+})();
@@ -0,0 +1,225 @@
+//! A minimal HTTP server standing in for remote services in tests.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use flate2::{Compression, write::GzEncoder};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use url::Url;
+
+#[derive(Clone, Debug)]
+pub struct TestResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl TestResponse {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn json<S>(body: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(200)
+            .with_header("Content-Type", "application/json")
+            .with_body(body.into())
+    }
+
+    /// A response with a gzipped body, as served by the Rebrickable CDN.
+    pub fn gzipped(body: &[u8]) -> Self {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(body)
+            .expect("writing to a vector succeeds");
+        Self::new(200).with_body(encoder.finish().expect("writing to a vector succeeds"))
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn with_body<B>(mut self, body: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        self.body = body.into();
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TestRequest {
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl TestRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: HashMap<String, VecDeque<TestResponse>>,
+    requests: Vec<TestRequest>,
+}
+
+/// A server answering requests with canned responses, routed by path and
+/// query string, or by path only if no route matches the query string. When
+/// several responses are registered for the same route, they are served in
+/// order, the last one being repeated.
+#[derive(Debug)]
+pub struct TestServer {
+    base_url: Url,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    pub async fn start() -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let task = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle_connection(stream, Arc::clone(&state)));
+                }
+            }
+        });
+        Ok(Self {
+            base_url,
+            state,
+            task,
+        })
+    }
+
+    pub fn url(&self, path: &str) -> Url {
+        self.base_url.join(path).expect("test URL is valid")
+    }
+
+    pub fn route(&self, path: &str, response: TestResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .entry(path.to_owned())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Replaces the responses of a route.
+    pub fn reroute(&self, path: &str, response: TestResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .insert(path.to_owned(), VecDeque::from([response]));
+    }
+
+    /// Serves the gzipped fixture tables from `src/rebrickable/fixtures`, as
+    /// the Rebrickable CDN does.
+    pub fn route_tables(&self) -> anyhow::Result<()> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/rebrickable/fixtures");
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            self.route(
+                &format!("/{file_name}.gz"),
+                TestResponse::gzipped(&fs::read(&path)?).with_header("ETag", "\"fixture\""),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn requests(&self) -> Vec<TestRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+    while !buf.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_owned();
+    let path = request_line.next().unwrap_or_default().to_owned();
+    let headers = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_owned(), value.trim().to_owned()))
+        .collect();
+
+    let response = {
+        let mut state = state.lock().unwrap();
+        let route = if state.routes.contains_key(&path) {
+            path.as_str()
+        } else {
+            path.split_once('?').map_or(path.as_str(), |(path, _)| path)
+        };
+        let response = match state.routes.get_mut(route) {
+            Some(responses) if responses.len() > 1 => responses.pop_front(),
+            Some(responses) => responses.front().cloned(),
+            None => None,
+        };
+        state.requests.push(TestRequest {
+            method: method.clone(),
+            path,
+            headers,
+        });
+        response.unwrap_or_else(|| TestResponse::new(404))
+    };
+
+    let mut head = format!(
+        "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    let _ = stream.write_all(head.as_bytes()).await;
+    if method != "HEAD" {
+        let _ = stream.write_all(&response.body).await;
+    }
+    let _ = stream.shutdown().await;
+}
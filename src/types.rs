@@ -86,6 +86,33 @@ impl fmt::Display for PartRelationType {
     }
 }
 
+impl FromStr for PartRelationType {
+    type Err = ParsePartRelationTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "print" => Ok(Self::Print),
+            "pair" => Ok(Self::Pair),
+            "subpart" => Ok(Self::SubPart),
+            "mold" => Ok(Self::Mold),
+            "pattern" => Ok(Self::Pattern),
+            "alternate" => Ok(Self::Alternate),
+            _ => Err(ParsePartRelationTypeError(())),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ParsePartRelationTypeError(());
+
+impl fmt::Display for ParsePartRelationTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid part relation type")
+    }
+}
+
+impl std::error::Error for ParsePartRelationTypeError {}
+
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum PartMaterial {
     CardboardPaper,
@@ -117,11 +144,81 @@ impl fmt::Display for PartMaterial {
     }
 }
 
+/// What an inventory describes.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum InventoryKind {
+    Set,
+    Minifig,
+}
+
+impl InventoryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Set => "set",
+            Self::Minifig => "minifig",
+        }
+    }
+}
+
+impl fmt::Display for InventoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which versions of the inventory of a set to consider.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum InventoryVersion {
+    /// The latest version only.
+    #[default]
+    Latest,
+    /// Every version.
+    All,
+    /// A specific version.
+    Exact(u32),
+}
+
+impl fmt::Display for InventoryVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Latest => f.write_str("latest"),
+            Self::All => f.write_str("all"),
+            Self::Exact(version) => write!(f, "{version}"),
+        }
+    }
+}
+
+impl FromStr for InventoryVersion {
+    type Err = ParseInventoryVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(Self::Latest),
+            "all" => Ok(Self::All),
+            _ => s
+                .parse()
+                .map(Self::Exact)
+                .map_err(|_| ParseInventoryVersionError(())),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ParseInventoryVersionError(());
+
+impl fmt::Display for ParseInventoryVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid inventory version, expected `latest`, `all` or a number")
+    }
+}
+
+impl std::error::Error for ParseInventoryVersionError {}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
-    use super::Rgb;
+    use super::{InventoryVersion, Rgb};
 
     #[test]
     fn from_str() -> anyhow::Result<()> {
@@ -138,4 +235,17 @@ mod tests {
         assert!(Rgb::from_str("#ff00fg").is_err());
         Ok(())
     }
+
+    #[test]
+    fn inventory_version_from_str() -> anyhow::Result<()> {
+        assert_eq!(
+            InventoryVersion::from_str("latest")?,
+            InventoryVersion::Latest
+        );
+        assert_eq!(InventoryVersion::from_str("all")?, InventoryVersion::All);
+        assert_eq!(InventoryVersion::from_str("2")?, InventoryVersion::Exact(2));
+        assert!(InventoryVersion::from_str("-1").is_err());
+        assert!(InventoryVersion::from_str("first").is_err());
+        Ok(())
+    }
 }